@@ -1,5 +1,5 @@
 use archipelago_rs::client::ArchipelagoClient;
-use archipelago_rs::protocol::ItemsHandlingFlags;
+use archipelago_rs::profile::ConnectProfile;
 use serde_json::Value;
 use std::io::{self, BufRead};
 
@@ -16,13 +16,7 @@ async fn main() -> anyhow::Result<()> {
     let game = prompt("What game?")?;
     let slot = prompt("What slot?")?;
     client
-        .connect(
-            &game,
-            &slot,
-            None,
-            ItemsHandlingFlags::all(),
-            vec!["AP".to_string()],
-        )
+        .connect_with_profile(&game, &slot, None, ConnectProfile::game_client())
         .await?;
     println!("Connected to slot!");
 
@@ -0,0 +1,133 @@
+//! An on-disk cache of data package games, keyed by their checksums.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::checksum;
+use crate::client::ArchipelagoError;
+use crate::protocol::GameData;
+
+/// Stores each game's [GameData] on disk under its checksum, so that only
+/// games whose checksum is unknown need to be downloaded from the server.
+///
+/// Each game is stored as `{dir}/{game}/{checksum}.json`, with characters
+/// that aren't safe in file names replaced in the game's name.
+#[derive(Debug, Clone)]
+pub struct DataPackageCache {
+    dir: PathBuf,
+}
+
+impl DataPackageCache {
+    pub fn new(dir: impl Into<PathBuf>) -> DataPackageCache {
+        DataPackageCache { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the cached data for [game] with [checksum], or `None` if it
+    /// isn't cached, can't be read, or doesn't match the checksum.
+    pub fn load(&self, game: &str, checksum: &str) -> Option<GameData> {
+        let json = fs::read_to_string(self.path(game, checksum)?).ok()?;
+        let data: GameData = serde_json::from_str(&json).ok()?;
+        (data.checksum == checksum && checksum::verify(game, &data).is_ok()).then_some(data)
+    }
+
+    /// Stores [data] for [game] under its checksum, after verifying that it
+    /// matches it.
+    pub fn store(&self, game: &str, data: &GameData) -> Result<(), ArchipelagoError> {
+        checksum::verify(game, data)?;
+        let Some(path) = self.path(game, &data.checksum) else {
+            // A checksum that isn't safe to use as a file name can't be
+            // cached, but the data is still usable.
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(data)?)?;
+        Ok(())
+    }
+
+    fn path(&self, game: &str, checksum: &str) -> Option<PathBuf> {
+        if checksum.is_empty() || !checksum.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        let game: String = game
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || " -_.".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        // Names like "." or ".." would escape the game's directory.
+        let game = game.trim_matches('.');
+        Some(
+            self.dir
+                .join(if game.is_empty() { "_" } else { game })
+                .join(format!("{checksum}.json")),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archipelago() -> GameData {
+        serde_json::from_str(
+            r#"{
+                "item_name_to_id": {"Nothing": -1},
+                "location_name_to_id": {"Cheat Console": -1, "Server": -2},
+                "item_name_groups": {"Everything": ["Nothing"]},
+                "location_name_groups": {"Everywhere": ["Cheat Console", "Server"]},
+                "checksum": "ac9141e9ad0318df2fa27da5f20c50a842afeecb"
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn cache(name: &str) -> DataPackageCache {
+        let dir = std::env::temp_dir().join(format!(
+            "archipelago_rs-cache-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        DataPackageCache::new(dir)
+    }
+
+    #[test]
+    fn stores_and_loads_a_game() {
+        let cache = cache("round-trip");
+        let data = archipelago();
+        cache.store("Archipelago", &data).unwrap();
+
+        let loaded = cache.load("Archipelago", &data.checksum).unwrap();
+        assert_eq!(loaded.item_name_to_id, data.item_name_to_id);
+        assert!(loaded
+            .location_name_to_id
+            .keys()
+            .eq(data.location_name_to_id.keys()));
+        assert!(cache.load("Archipelago", "0123abcd").is_none());
+        assert!(cache.load("Other Game", &data.checksum).is_none());
+        let _ = fs::remove_dir_all(cache.dir());
+    }
+
+    #[test]
+    fn refuses_to_store_a_mismatched_game() {
+        let cache = cache("mismatch");
+        let mut data = archipelago();
+        data.item_name_to_id
+            .insert("Something".to_string(), 1.into());
+        assert!(matches!(
+            cache.store("Archipelago", &data),
+            Err(ArchipelagoError::ChecksumMismatch { .. })
+        ));
+        assert!(cache.load("Archipelago", &data.checksum).is_none());
+        let _ = fs::remove_dir_all(cache.dir());
+    }
+}
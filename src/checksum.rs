@@ -0,0 +1,136 @@
+//! Verifying data package checksums.
+
+use std::collections::{BTreeMap, HashMap};
+
+use indexmap::IndexMap;
+
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+
+use crate::client::ArchipelagoError;
+use crate::protocol::{GameData, ItemId, LocationId};
+
+/// The data a game's checksum covers, with its fields in sorted order. The
+/// server sorts the name groups and their members, but hashes the ID maps in
+/// the game's own order.
+#[derive(Serialize)]
+struct ChecksumData<'a> {
+    item_name_groups: BTreeMap<&'a str, Vec<&'a str>>,
+    item_name_to_id: &'a IndexMap<String, ItemId>,
+    location_name_groups: BTreeMap<&'a str, Vec<&'a str>>,
+    location_name_to_id: &'a IndexMap<String, LocationId>,
+}
+
+/// Computes the checksum of [data] the same way the Archipelago server does:
+/// the SHA-1 of its compact JSON encoding, with the name groups sorted and
+/// the ID maps in the order they were received.
+///
+/// Missing name groups are hashed as empty, so the result only matches the
+/// server's once the groups have been filled in.
+pub fn compute(data: &GameData) -> String {
+    let checksum_data = ChecksumData {
+        item_name_groups: sorted_groups(data.item_name_groups.as_ref()),
+        item_name_to_id: &data.item_name_to_id,
+        location_name_groups: sorted_groups(data.location_name_groups.as_ref()),
+        location_name_to_id: &data.location_name_to_id,
+    };
+    let json = serde_json::to_string(&checksum_data).expect("checksum data always serializes");
+    Sha1::digest(json.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Returns an error unless [data] for [game] matches its own checksum.
+pub fn verify(game: &str, data: &GameData) -> Result<(), ArchipelagoError> {
+    let actual = compute(data);
+    if actual != data.checksum {
+        return Err(ArchipelagoError::ChecksumMismatch {
+            game: game.to_string(),
+            expected: data.checksum.clone(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+fn sorted_groups(groups: Option<&HashMap<String, Vec<String>>>) -> BTreeMap<&str, Vec<&str>> {
+    groups
+        .into_iter()
+        .flatten()
+        .map(|(name, members)| {
+            let mut members: Vec<&str> = members.iter().map(String::as_str).collect();
+            members.sort_unstable();
+            (name.as_str(), members)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(json: &str) -> GameData {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn matches_the_archipelago_game() {
+        let data = game(
+            r#"{
+                "item_name_to_id": {"Nothing": -1},
+                "location_name_to_id": {"Cheat Console": -1, "Server": -2},
+                "item_name_groups": {"Everything": ["Nothing"]},
+                "location_name_groups": {"Everywhere": ["Cheat Console", "Server"]},
+                "checksum": "ac9141e9ad0318df2fa27da5f20c50a842afeecb"
+            }"#,
+        );
+        assert_eq!(compute(&data), data.checksum);
+        assert!(verify("Archipelago", &data).is_ok());
+    }
+
+    #[test]
+    fn hashes_ids_in_received_order_and_groups_sorted() {
+        let data = game(
+            r#"{
+                "item_name_to_id": {"Sword": 2, "Bow": 1, "Épée": 3},
+                "location_name_to_id": {"Z Room": 10, "A Room": 11},
+                "item_name_groups": {
+                    "Weapons": ["Sword", "Bow", "Épée"],
+                    "Everything": ["Sword", "Bow", "Épée"]
+                },
+                "location_name_groups": {"Everywhere": ["Z Room", "A Room"]},
+                "checksum": "77e4012a407d4055c37b499d31c98cc8326406e9"
+            }"#,
+        );
+        assert_eq!(compute(&data), data.checksum);
+
+        let mut reordered = data.clone();
+        reordered.item_name_to_id.sort_keys();
+        assert!(matches!(
+            verify("Test", &reordered),
+            Err(ArchipelagoError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn missing_groups_hash_as_empty() {
+        let mut data = game(
+            r#"{
+                "item_name_to_id": {"Nothing": -1},
+                "location_name_to_id": {"Cheat Console": -1, "Server": -2},
+                "checksum": "ac9141e9ad0318df2fa27da5f20c50a842afeecb"
+            }"#,
+        );
+        assert_ne!(compute(&data), data.checksum);
+        data.item_name_groups = Some(HashMap::from([(
+            "Everything".to_string(),
+            vec!["Nothing".to_string()],
+        )]));
+        data.location_name_groups = Some(HashMap::from([(
+            "Everywhere".to_string(),
+            vec!["Server".to_string(), "Cheat Console".to_string()],
+        )]));
+        assert_eq!(compute(&data), data.checksum);
+    }
+}
@@ -1,3 +1,7 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use futures_util::{
     stream::{SplitSink, SplitStream},
     SinkExt, Stream, StreamExt,
@@ -7,7 +11,27 @@ use tokio::net::TcpStream;
 use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
 use tungstenite::protocol::Message;
 
+use crate::cache::DataPackageCache;
+use crate::checksum;
+use crate::deathlink::DeathLink;
+use crate::energylink::EnergyLink;
+use crate::gifting::{Gift, GiftBoxInfo, Gifting};
+use crate::hints::{HintBook, HintPoints};
+use crate::inventory::Inventory;
+use crate::items::{ItemDelivery, ReceiveOutcome, ReceivedItem};
+use crate::links::{BounceFilter, Link};
+use crate::lookup::NameLookup;
+use crate::outbox::LocationOutbox;
+use crate::profile::ConnectProfile;
 use crate::protocol::*;
+use crate::session::SessionSnapshot;
+use crate::storage::{
+    self, DataStorage, KeyChange, NameGroups, SetBuilder, StorageMirror, Subscription,
+};
+use crate::text::TextResolver;
+
+/// The additional Set field that identifies which SetReply answers it.
+const REPLY_ID_FIELD: &str = "archipelago_rs_reply_id";
 
 #[derive(Error, Debug)]
 pub enum ArchipelagoError {
@@ -29,6 +53,121 @@ pub enum ArchipelagoError {
     NonTextWebsocketResult(Message),
     #[error("network error")]
     NetworkError(#[from] tungstenite::Error),
+    #[error("invalid items handling flags {0:?}: remote items require OTHER_WORLDS")]
+    InvalidItemsHandling(ItemsHandlingFlags),
+    #[error("not connected to a slot")]
+    NotConnected,
+    #[error("session snapshot doesn't match the server: {0}")]
+    SessionMismatch(String),
+    #[error("i/o error ({0})")]
+    Io(#[from] std::io::Error),
+    #[error("data package checksum mismatch for {game}: expected {expected}, computed {actual}")]
+    ChecksumMismatch {
+        game: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("received items aren't being tracked")]
+    NotTrackingItems,
+    #[error("can't split a client that's tracking {0}")]
+    SplitWhileTracking(&'static str),
+}
+
+/// Something that happened while the client was processing server messages,
+/// returned by `ArchipelagoClient::take_events`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ClientEvent {
+    /// The room's data package checksums changed, and the changed [games]
+    /// have been fetched again and merged into the data package.
+    DataPackageRefreshed { games: Vec<String> },
+
+    /// `resume` connected to a room whose data package for [games] has a
+    /// different checksum than when the snapshot was taken, so item and
+    /// location IDs saved for those games may mean something else.
+    DataPackageChangedSinceSnapshot { games: Vec<String> },
+
+    /// The server resent its full item list, and it has only [received]
+    /// items, fewer than the [applied] the game has already applied. The save
+    /// probably belongs to another slot or seed, and no items are delivered
+    /// until the list grows past the applied index.
+    ItemsReset { received: i64, applied: i64 },
+
+    /// A game in a Data Package received from the server didn't match its
+    /// checksum, so it was dropped from the data package.
+    ChecksumMismatch {
+        game: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+/// What the client knows about the slot it's connected to.
+///
+/// This is populated from the Connected message and kept current from
+/// RoomUpdate messages.
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    pub team: TeamId,
+    pub slot: SlotId,
+    pub players: Vec<NetworkPlayer>,
+    pub slot_info: HashMap<SlotId, NetworkSlot>,
+    pub checked_locations: HashSet<LocationId>,
+    pub missing_locations: HashSet<LocationId>,
+    pub hint_points: i64,
+}
+
+impl ConnectionInfo {
+    fn new<S>(connected: &Connected<S>) -> ConnectionInfo {
+        ConnectionInfo {
+            team: connected.team,
+            slot: connected.slot,
+            players: connected.players.clone(),
+            slot_info: connected.slot_info.clone(),
+            checked_locations: connected.checked_locations.iter().copied().collect(),
+            missing_locations: connected.missing_locations.iter().copied().collect(),
+            hint_points: connected.hint_points,
+        }
+    }
+
+    fn apply_update(&mut self, update: &RoomUpdate) {
+        if let Some(players) = &update.players {
+            self.players = players.clone();
+        }
+        if let Some(checked_locations) = &update.checked_locations {
+            for location in checked_locations {
+                self.missing_locations.remove(location);
+                self.checked_locations.insert(*location);
+            }
+        }
+        if let Some(missing_locations) = &update.missing_locations {
+            self.missing_locations.extend(missing_locations);
+        }
+        if let Some(hint_points) = update.hint_points {
+            self.hint_points = hint_points;
+        }
+    }
+
+    /// The game played on the connected slot.
+    pub fn game(&self) -> Option<&str> {
+        self.slot_info
+            .get(&self.slot)
+            .map(|slot| slot.game.as_str())
+    }
+
+    /// The name of the connected slot.
+    pub fn name(&self) -> Option<&str> {
+        self.slot_info
+            .get(&self.slot)
+            .map(|slot| slot.name.as_str())
+    }
+
+    /// Returns the player on this slot's team with the given slot number.
+    pub fn player(&self, slot: SlotId) -> Option<&NetworkPlayer> {
+        self.players
+            .iter()
+            .find(|player| player.team == self.team && player.slot == slot)
+    }
 }
 
 /// The client that talks to the Archipelago server using the Archipelago
@@ -45,6 +184,24 @@ where
     room_info: RoomInfo,
     message_buffer: Vec<ServerMessage<S>>,
     data_package: Option<DataPackageObject>,
+    storage: StorageMirror,
+    connection: Option<ConnectionInfo>,
+    hint_book: Option<HintBook>,
+    item_delivery: Option<ItemDelivery>,
+    inventory: Inventory,
+    sync_needed: bool,
+    tags: Vec<Tag>,
+    items_handling: ItemsHandlingFlags,
+    outbox: LocationOutbox,
+    resend_needed: bool,
+    lookup: Option<NameLookup>,
+    stale_games: HashSet<String>,
+    refreshing_games: HashSet<String>,
+    awaiting_groups: HashMap<String, GameData>,
+    unverified_games: HashSet<String>,
+    cache: Option<DataPackageCache>,
+    events: Vec<ClientEvent>,
+    sets_sent: u64,
 }
 
 impl<S> ArchipelagoClient<S>
@@ -80,12 +237,39 @@ where
             None => return Err(ArchipelagoError::ConnectionClosed),
         };
 
-        Ok(ArchipelagoClient {
+        let mut client = ArchipelagoClient {
             ws,
             room_info,
-            message_buffer: iter.collect(),
+            message_buffer: Vec::new(),
             data_package: None,
-        })
+            storage: StorageMirror::default(),
+            connection: None,
+            hint_book: None,
+            item_delivery: None,
+            inventory: Inventory::default(),
+            sync_needed: false,
+            tags: Vec::new(),
+            items_handling: ItemsHandlingFlags::empty(),
+            outbox: LocationOutbox::default(),
+            resend_needed: false,
+            lookup: None,
+            stale_games: HashSet::new(),
+            refreshing_games: HashSet::new(),
+            awaiting_groups: HashMap::new(),
+            unverified_games: HashSet::new(),
+            cache: None,
+            events: Vec::new(),
+            sets_sent: 0,
+        };
+        let mut messages: Vec<_> = iter.collect();
+        for message in &messages {
+            client.handle_incoming(message);
+        }
+        // `recv` pops messages from the back of the buffer.
+        messages.reverse();
+        client.message_buffer = messages;
+
+        Ok(client)
     }
 
     /**
@@ -100,9 +284,10 @@ where
         client
             .send(ClientMessage::GetDataPackage(GetDataPackage { games }))
             .await?;
+        // The package itself is stored as the message is received.
         let response = client.recv().await?;
         match response {
-            Some(ServerMessage::DataPackage(pkg)) => client.data_package = Some(pkg.data),
+            Some(ServerMessage::DataPackage(_)) => {}
             Some(received) => return Err(Self::illegal_response("DataPackage", received)),
             None => return Err(ArchipelagoError::ConnectionClosed),
         }
@@ -110,6 +295,102 @@ where
         Ok(client)
     }
 
+    /**
+     * Create an instance of the client and connect to the server, loading the Data Package from
+     * the given cache and fetching only the games whose checksums aren't cached
+     */
+    pub async fn with_cached_data_package(
+        url: &str,
+        cache: &DataPackageCache,
+    ) -> Result<ArchipelagoClient<S>, ArchipelagoError> {
+        let mut client = Self::new(url).await?;
+        client.load_data_package(cache).await?;
+
+        Ok(client)
+    }
+
+    /**
+     * Bring the Data Package up to date with the room's checksums
+     *
+     * Once a Data Package has been loaded, `recv` also keeps it current by fetching the games
+     * whose checksums change in a RoomUpdate, reporting them with a DataPackageRefreshed event.
+     * Those games are verified and written to [cache] along with their name groups
+     *
+     * Games that are already loaded with the current checksum are kept, others are loaded from
+     * the cache, and the rest are fetched from the server. Non-DataPackage responses are buffered
+     *
+     * Games are verified against their checksums before they're added to the cache, and a game
+     * that doesn't match is dropped from the Data Package with a ChecksumMismatch error. Since
+     * the server only sends a game's name groups (which the checksum covers) to connected
+     * clients, games fetched before connecting are verified and cached the next time this is
+     * called after connecting
+     */
+    pub async fn load_data_package(
+        &mut self,
+        cache: &DataPackageCache,
+    ) -> Result<(), ArchipelagoError> {
+        self.cache = Some(cache.clone());
+        let mut cached = HashMap::new();
+        let mut missing = Vec::new();
+        for (game, checksum) in &self.room_info.datapackage_checksums {
+            let loaded = self
+                .data_package
+                .as_ref()
+                .and_then(|package| package.games.get(game))
+                .is_some_and(|data| &data.checksum == checksum);
+            if loaded {
+                continue;
+            }
+            match cache.load(game, checksum) {
+                Some(data) => {
+                    cached.insert(game.clone(), data);
+                }
+                None => missing.push(game.clone()),
+            }
+        }
+        self.merge_data_package(cached);
+
+        if !missing.is_empty() {
+            self.fetch_data_package(missing).await?;
+        }
+
+        // Games received with their name groups are verified as they're
+        // merged, so only those without them are left.
+        let unverified: Vec<String> = self.unverified_games.iter().cloned().collect();
+        for game in unverified {
+            let Some(mut data) = self
+                .data_package
+                .as_ref()
+                .and_then(|package| package.games.get(&game))
+                .cloned()
+            else {
+                self.unverified_games.remove(&game);
+                continue;
+            };
+            if data.item_name_groups.is_none() || data.location_name_groups.is_none() {
+                // The name groups are part of the checksum, but the server
+                // only hands them out to connected clients.
+                if self.connection.is_none() {
+                    continue;
+                }
+                data.item_name_groups = Some(self.read_item_name_groups(&game).await?);
+                data.location_name_groups = Some(self.read_location_name_groups(&game).await?);
+            }
+            self.unverified_games.remove(&game);
+            if let Err(error) = checksum::verify(&game, &data) {
+                if let Some(package) = &mut self.data_package {
+                    package.games.remove(&game);
+                }
+                self.rebuild_lookup();
+                return Err(error);
+            }
+            cache.store(&game, &data)?;
+            self.merge_data_package(HashMap::from([(game, data)]));
+        }
+
+        Ok(())
+    }
+
     pub fn room_info(&self) -> &RoomInfo {
         &self.room_info
     }
@@ -118,6 +399,136 @@ where
         self.data_package.as_ref()
     }
 
+    /// Returns whether [game] is in the data package without having been
+    /// verified against its checksum, because it was received without its
+    /// name groups. `load_data_package` verifies such games once connected.
+    pub fn is_unverified(&self, game: &str) -> bool {
+        self.unverified_games.contains(game)
+    }
+
+    /// Returns what's known about the connected slot, or `None` before
+    /// `connect` has succeeded.
+    pub fn connection_info(&self) -> Option<&ConnectionInfo> {
+        self.connection.as_ref()
+    }
+
+    /// Returns the connected slot's hints, or `None` until `track_hints` has
+    /// been called.
+    pub fn hint_book(&self) -> Option<&HintBook> {
+        self.hint_book.as_ref()
+    }
+
+    /// Returns the connected slot's hint points and the current hint cost,
+    /// or `None` before `connect` has succeeded.
+    pub fn hint_points(&self) -> Option<HintPoints> {
+        let connection = self.connection.as_ref()?;
+        Some(HintPoints::new(
+            connection.hint_points,
+            self.room_info.hint_cost,
+            connection.checked_locations.len() + connection.missing_locations.len(),
+            self.room_info.location_check_points,
+        ))
+    }
+
+    /// Returns every item the connected slot has received.
+    pub fn inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    /// Returns the location checks that haven't been acknowledged by the
+    /// server yet. Save it to keep checks made while offline.
+    pub fn outbox(&self) -> &LocationOutbox {
+        &self.outbox
+    }
+
+    /**
+     * Restore a saved outbox, whose checks will be sent once the client connects.
+     *
+     * Any checks already queued in this client are kept.
+     */
+    pub fn restore_outbox(&mut self, outbox: LocationOutbox) {
+        self.outbox.queue(outbox.pending().iter().copied());
+    }
+
+    /// Returns the item delivery state, or `None` if `track_items` hasn't been
+    /// called. Its applied index should be saved alongside the game's save
+    /// file.
+    pub fn item_delivery(&self) -> Option<&ItemDelivery> {
+        self.item_delivery.as_ref()
+    }
+
+    /**
+     * Start handing received items to the game exactly once.
+     *
+     * `delivery` carries how many items the game has already applied, and should be set up
+     * before `connect` so that the server's initial full resend is filtered. Whenever items go
+     * missing, a Sync is sent automatically (as it is for keeping `inventory` current).
+     */
+    pub fn track_items(&mut self, delivery: ItemDelivery) {
+        self.item_delivery = Some(delivery);
+    }
+
+    /**
+     * Take the received items the game hasn't applied yet, in order, and mark them as applied.
+     *
+     * Always empty unless `track_items` was called. If the server's items don't reach the applied
+     * index, an ItemsReset event is reported instead.
+     */
+    pub fn take_new_items(&mut self) -> Vec<NetworkItem> {
+        self.item_delivery
+            .as_mut()
+            .map(ItemDelivery::take)
+            .unwrap_or_default()
+    }
+
+    /**
+     * Take the events that happened since the last call, in order.
+     */
+    pub fn take_events(&mut self) -> Vec<ClientEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Returns a resolver that renders PrintJSON messages with player, item
+    /// and location names, or `None` before `connect` has succeeded.
+    pub fn text_resolver(&self) -> Option<TextResolver<'_>> {
+        let connection = self.connection.as_ref()?;
+        Some(TextResolver::new(connection, self.lookup.as_ref()))
+    }
+
+    /**
+     * Take the received items the game hasn't applied yet, like `take_new_items`, described with
+     * their sender, location and origin.
+     *
+     * Names that can't be resolved are filled in the way `text_resolver` renders them. Always
+     * empty unless `track_items` has been called, and before `connect` has succeeded.
+     */
+    pub fn take_received_items(&mut self) -> Vec<ReceivedItem> {
+        // Items are left pending until names can be resolved.
+        let Some(connection) = &self.connection else {
+            return Vec::new();
+        };
+        let resolver = TextResolver::new(connection, self.lookup.as_ref());
+        self.item_delivery
+            .as_mut()
+            .map(ItemDelivery::take)
+            .unwrap_or_default()
+            .iter()
+            .map(|item| ReceivedItem::new(item, &resolver))
+            .collect()
+    }
+
+    /// Returns item and location name lookups for every player, or `None`
+    /// until both the data package has been fetched and `connect` has
+    /// succeeded.
+    pub fn lookup(&self) -> Option<&NameLookup> {
+        self.lookup.as_ref()
+    }
+
+    /// Returns the local copy of the data storage keys this client watches.
+    pub fn storage_mirror(&self) -> &StorageMirror {
+        &self.storage
+    }
+
     pub async fn send(&mut self, message: ClientMessage) -> Result<(), ArchipelagoError> {
         let request = serde_json::to_string(&[message])?;
         self.ws.send(Message::Text(request.into())).await?;
@@ -138,6 +549,38 @@ where
         let messages = recv_messages(&mut self.ws).await;
         if let Some(result) = messages {
             let mut messages = result?;
+            for message in &messages {
+                self.handle_incoming(message);
+            }
+            if self.sync_needed {
+                self.sync_needed = false;
+                self.send(ClientMessage::Sync).await?;
+            }
+            if self.resend_needed {
+                self.resend_needed = false;
+                let locations = self.outbox.pending().iter().copied().collect();
+                self.location_checks(locations).await?;
+            }
+            if !self.stale_games.is_empty() {
+                let games: Vec<String> = self.stale_games.drain().collect();
+                self.refreshing_games.extend(games.iter().cloned());
+                // RoomUpdates only reach connected clients, so the name
+                // groups needed to verify the games can be requested too.
+                let keys = games
+                    .iter()
+                    .flat_map(|game| {
+                        [
+                            DataStorage::item_name_groups(game).key().to_string(),
+                            DataStorage::location_name_groups(game).key().to_string(),
+                        ]
+                    })
+                    .collect();
+                self.send(ClientMessage::GetDataPackage(GetDataPackage {
+                    games: Some(games),
+                }))
+                .await?;
+                self.send(ClientMessage::Get(Get { keys })).await?;
+            }
             messages.reverse();
             let first = messages.pop();
             self.message_buffer = messages;
@@ -159,15 +602,20 @@ where
         name: &str,
         password: Option<&str>,
         items_handling: ItemsHandlingFlags,
-        tags: Vec<String>,
+        tags: Vec<Tag>,
     ) -> Result<Connected<S>, ArchipelagoError> {
+        if !items_handling.is_valid() {
+            return Err(ArchipelagoError::InvalidItemsHandling(items_handling));
+        }
+        self.tags = tags.clone();
+        self.items_handling = items_handling;
         self.send(ClientMessage::Connect(Connect {
             game: game.to_string(),
             name: name.to_string(),
             uuid: "".to_string(),
             password: password.map(|p| p.to_string()),
             version: network_version(),
-            items_handling: items_handling.bits(),
+            items_handling,
             tags,
             slot_data: true,
         }))
@@ -183,15 +631,169 @@ where
         }
     }
 
+    /**
+     * Send a connect request using the tags and item handling of the given profile
+     *
+     * See `connect` for how the response is handled
+     */
+    pub async fn connect_with_profile(
+        &mut self,
+        game: &str,
+        name: &str,
+        password: Option<&str>,
+        profile: ConnectProfile,
+    ) -> Result<Connected<S>, ArchipelagoError> {
+        self.connect(game, name, password, profile.items_handling, profile.tags)
+            .await
+    }
+
+    /**
+     * Update the item handling and tags of an already connected client
+     */
+    pub async fn connect_update(
+        &mut self,
+        items_handling: ItemsHandlingFlags,
+        tags: Vec<Tag>,
+    ) -> Result<(), ArchipelagoError> {
+        if !items_handling.is_valid() {
+            return Err(ArchipelagoError::InvalidItemsHandling(items_handling));
+        }
+        self.tags = tags.clone();
+        self.items_handling = items_handling;
+        self.send(ClientMessage::ConnectUpdate(ConnectUpdate {
+            items_handling,
+            tags,
+        }))
+        .await
+    }
+
+    /**
+     * Reconnect to the slot a session snapshot was taken from.
+     *
+     * Refuses to connect if the room is hosting a different seed, and returns an error after
+     * connecting if the server put the client on a different team or slot. Received items are
+     * tracked from the snapshot's item index, and once the connection is checked, the snapshot's
+     * checked locations go through the outbox so that any the server doesn't know about are sent
+     * again. Games whose data package changed since the snapshot was taken are reported with a
+     * DataPackageChangedSinceSnapshot event.
+     *
+     * If connecting fails, the client's previous item tracking and queued checks are restored.
+     */
+    pub async fn resume(
+        &mut self,
+        snapshot: &SessionSnapshot,
+        password: Option<&str>,
+    ) -> Result<Connected<S>, ArchipelagoError> {
+        snapshot.check_room(&self.room_info)?;
+        let tracked = self
+            .item_delivery
+            .replace(ItemDelivery::new(snapshot.item_index));
+        // Hold back already queued checks too, so that nothing is sent until
+        // the connection is known to be the snapshot's.
+        let queued = std::mem::take(&mut self.outbox);
+        let connected = self
+            .connect(
+                &snapshot.game,
+                &snapshot.name,
+                password,
+                snapshot.items_handling,
+                snapshot.tags.clone(),
+            )
+            .await
+            .and_then(|connected| {
+                let connection = self
+                    .connection
+                    .as_ref()
+                    .ok_or(ArchipelagoError::NotConnected)?;
+                snapshot.check_connection(connection)?;
+                Ok(connected)
+            });
+        let connected = match connected {
+            Ok(connected) => connected,
+            Err(error) => {
+                self.item_delivery = tracked;
+                self.outbox = queued;
+                return Err(error);
+            }
+        };
+        let mut changed: Vec<String> = snapshot
+            .datapackage_checksums
+            .iter()
+            .filter(|(game, checksum)| {
+                self.room_info
+                    .datapackage_checksums
+                    .get(*game)
+                    .is_some_and(|current| current != *checksum)
+            })
+            .map(|(game, _)| game.clone())
+            .collect();
+        if !changed.is_empty() {
+            changed.sort();
+            self.events
+                .push(ClientEvent::DataPackageChangedSinceSnapshot { games: changed });
+        }
+        let locations = queued
+            .pending()
+            .iter()
+            .chain(&snapshot.checked_locations)
+            .copied()
+            .collect();
+        self.queue_location_checks(locations).await?;
+
+        Ok(connected)
+    }
+
+    /**
+     * Take a snapshot of the current session, to be saved alongside the game's save file and
+     * later passed to `resume`.
+     *
+     * Fails unless received items are being tracked with `track_items`, since the snapshot's item
+     * index is the index of the last item the game has applied.
+     */
+    pub fn snapshot(&self) -> Result<SessionSnapshot, ArchipelagoError> {
+        let connection = self
+            .connection
+            .as_ref()
+            .ok_or(ArchipelagoError::NotConnected)?;
+        let item_index = self
+            .item_delivery
+            .as_ref()
+            .ok_or(ArchipelagoError::NotTrackingItems)?
+            .applied_index();
+        let datapackage_checksums = self
+            .data_package
+            .iter()
+            .flat_map(|package| &package.games)
+            .map(|(game, data)| (game.clone(), data.checksum.clone()))
+            .collect();
+
+        Ok(SessionSnapshot {
+            seed_name: self.room_info.seed_name.clone(),
+            game: connection.game().unwrap_or_default().to_string(),
+            name: connection.name().unwrap_or_default().to_string(),
+            team: connection.team,
+            slot: connection.slot,
+            item_index,
+            checked_locations: connection
+                .checked_locations
+                .iter()
+                .chain(self.outbox.pending())
+                .copied()
+                .collect(),
+            tags: self.tags.clone(),
+            items_handling: self.items_handling,
+            datapackage_checksums,
+        })
+    }
+
     /**
      * Basic chat command which sends text to the server to be distributed to other clients.
      */
     pub async fn say(&mut self, message: &str) -> Result<(), ArchipelagoError> {
-        Ok(self
-            .send(ClientMessage::Say(Say {
-                text: message.to_string(),
-            }))
-            .await?)
+        self.send(ClientMessage::Say(Say {
+            text: message.to_string(),
+        }))
+        .await
     }
 
     /**
@@ -201,14 +803,11 @@ where
      */
     pub async fn sync(&mut self) -> Result<ReceivedItems, ArchipelagoError> {
         self.send(ClientMessage::Sync).await?;
-        while let Some(response) = self.recv().await? {
-            match response {
-                ServerMessage::ReceivedItems(items) => return Ok(items),
-                resp => self.message_buffer.push(resp),
-            }
-        }
-
-        Err(ArchipelagoError::ConnectionClosed)
+        self.recv_until(|response| match response {
+            ServerMessage::ReceivedItems(items) => ControlFlow::Break(items),
+            resp => ControlFlow::Continue(resp),
+        })
+        .await
     }
 
     /**
@@ -216,10 +815,39 @@ where
      *
      * Used to inform the server of new checks that are made, as well as to sync state.
      */
-    pub async fn location_checks(&mut self, locations: Vec<i64>) -> Result<(), ArchipelagoError> {
-        Ok(self
-            .send(ClientMessage::LocationChecks(LocationChecks { locations }))
-            .await?)
+    pub async fn location_checks(
+        &mut self,
+        locations: Vec<LocationId>,
+    ) -> Result<(), ArchipelagoError> {
+        self.send(ClientMessage::LocationChecks(LocationChecks { locations }))
+            .await
+    }
+
+    /**
+     * Queue location checks in the outbox and send them if the client is connected.
+     *
+     * Unlike `location_checks`, the checks stay queued until the server reports them as checked,
+     * and are resent whenever the client connects until then. Locations the server has already
+     * reported as checked, and ones that aren't among the slot's locations, are dropped, since
+     * they'd never be acknowledged. When not connected, that happens once the client connects.
+     */
+    pub async fn queue_location_checks(
+        &mut self,
+        locations: Vec<LocationId>,
+    ) -> Result<(), ArchipelagoError> {
+        let locations = match &self.connection {
+            Some(connection) => locations
+                .into_iter()
+                .filter(|location| connection.missing_locations.contains(location))
+                .collect(),
+            None => locations,
+        };
+        let new = self.outbox.queue(locations);
+        if self.connection.is_some() && !new.is_empty() {
+            self.location_checks(new).await?;
+        }
+
+        Ok(())
     }
 
     /**
@@ -229,7 +857,7 @@ where
      */
     pub async fn location_scouts(
         &mut self,
-        locations: Vec<i64>,
+        locations: Vec<LocationId>,
         create_as_hint: u8,
     ) -> Result<LocationInfo, ArchipelagoError> {
         self.send(ClientMessage::LocationScouts(LocationScouts {
@@ -237,14 +865,30 @@ where
             create_as_hint,
         }))
         .await?;
-        while let Some(response) = self.recv().await? {
-            match response {
-                ServerMessage::LocationInfo(items) => return Ok(items),
-                resp => self.message_buffer.push(resp),
-            }
-        }
+        self.recv_until(|response| match response {
+            ServerMessage::LocationInfo(items) => ControlFlow::Break(items),
+            resp => ControlFlow::Continue(resp),
+        })
+        .await
+    }
 
-        Err(ArchipelagoError::ConnectionClosed)
+    /**
+     * Sent to the server to change the status of a hint, for example to mark it as a priority.
+     *
+     * The hint is identified by the player whose world contains the item and the location it's at.
+     */
+    pub async fn update_hint(
+        &mut self,
+        player: PlayerId,
+        location: LocationId,
+        status: HintStatus,
+    ) -> Result<(), ArchipelagoError> {
+        self.send(ClientMessage::UpdateHint(UpdateHint {
+            player,
+            location,
+            status,
+        }))
+        .await
     }
 
     /**
@@ -253,53 +897,126 @@ where
      * Examples include readiness or goal completion. (Example: defeated Ganon in A Link to the Past)
      */
     pub async fn status_update(&mut self, status: ClientStatus) -> Result<(), ArchipelagoError> {
-        Ok(self
-            .send(ClientMessage::StatusUpdate(StatusUpdate { status }))
-            .await?)
+        self.send(ClientMessage::StatusUpdate(StatusUpdate { status }))
+            .await
     }
 
     /**
      * Send this message to the server, tell it which clients should receive the message and the server will forward the message to all those targets to which any one requirement applies.
      */
-    pub async fn bounce(
+    pub async fn bounce<T>(
         &mut self,
         games: Option<Vec<String>>,
-        slots: Option<Vec<String>>,
-        tags: Option<Vec<String>>,
-        data: serde_json::Value,
-    ) -> Result<(), ArchipelagoError> {
-        Ok(self
-            .send(ClientMessage::Bounce(Bounce {
-                games,
-                slots,
-                tags,
-                data,
-            }))
-            .await?)
+        slots: Option<Vec<SlotId>>,
+        tags: Option<Vec<Tag>>,
+        data: T,
+    ) -> Result<(), ArchipelagoError>
+    where
+        T: serde::Serialize,
+    {
+        let bounce = Bounce {
+            games,
+            slots,
+            tags,
+            data,
+        };
+        self.send(ClientMessage::Bounce(bounce.into_untyped()?))
+            .await
     }
 
     /**
-     * Used to request a single or multiple values from the server's data storage, see the Set package for how to write values to the data storage.
+     * Wait for a Bounced message matching the filter and decode its payload as `T`.
      *
-     * A Get package will be answered with a Retrieved package. Non-Retrieved responses are
-     * buffered
+     * Non-matching responses are buffered
      */
-    pub async fn get(&mut self, keys: Vec<String>) -> Result<Retrieved, ArchipelagoError> {
-        self.send(ClientMessage::Get(Get { keys })).await?;
-        while let Some(response) = self.recv().await? {
-            match response {
-                ServerMessage::Retrieved(items) => return Ok(items),
-                resp => self.message_buffer.push(resp),
+    pub async fn recv_bounced<T>(
+        &mut self,
+        filter: &BounceFilter,
+    ) -> Result<Bounced<T>, ArchipelagoError>
+    where
+        T: for<'a> serde::de::Deserialize<'a>,
+    {
+        let bounced = self
+            .recv_until(|response| match response {
+                ServerMessage::Bounced(bounced) if filter.matches(&bounced) => {
+                    ControlFlow::Break(bounced)
+                }
+                resp => ControlFlow::Continue(resp),
+            })
+            .await?;
+        bounced
+            .decode()
+            .map_err(|error| ArchipelagoError::FailedDeserialize {
+                json: bounced.data.to_string(),
+                error,
+            })
+    }
+
+    /**
+     * Share an event with every other participant of a Bounce-based link, such as TrapLink.
+     */
+    pub async fn send_link<L>(
+        &mut self,
+        link: &L,
+        payload: &L::Payload,
+    ) -> Result<(), ArchipelagoError>
+    where
+        L: Link,
+    {
+        self.send(ClientMessage::Bounce(link.bounce(payload)?))
+            .await
+    }
+
+    /**
+     * Record a local death for DeathLink, and send it to other players unless it's forgiven by
+     * the DeathLink's amnesty.
+     *
+     * Returns whether the death was sent.
+     */
+    pub async fn send_death(
+        &mut self,
+        death_link: &mut DeathLink,
+        cause: Option<String>,
+    ) -> Result<bool, ArchipelagoError> {
+        match death_link.record_death(cause) {
+            Some(bounce) => {
+                self.send(ClientMessage::Bounce(bounce)).await?;
+                Ok(true)
             }
+            None => Ok(false),
         }
+    }
 
-        Err(ArchipelagoError::ConnectionClosed)
+    /**
+     * Used to request a single or multiple values from the server's data storage, see the Set package for how to write values to the data storage.
+     *
+     * A Get package will be answered with a Retrieved package. Other responses, including
+     * Retrieved packages that don't include every key, are buffered
+     */
+    pub async fn get(&mut self, keys: Vec<String>) -> Result<Retrieved, ArchipelagoError> {
+        self.send(ClientMessage::Get(Get { keys: keys.clone() }))
+            .await?;
+        // The client makes Gets of its own, whose replies only differ in
+        // their keys.
+        self.recv_until(|response| match response {
+            ServerMessage::Retrieved(items)
+                if items.keys.as_object().is_some_and(|retrieved| {
+                    keys.iter().all(|key| retrieved.contains_key(key))
+                }) =>
+            {
+                ControlFlow::Break(items)
+            }
+            resp => ControlFlow::Continue(resp),
+        })
+        .await
     }
 
     /**
      * Used to write data to the server's data storage, that data can then be shared across worlds or just saved for later.
      *
-     * Values for keys in the data storage can be retrieved with a Get package, or monitored with a SetNotify package. Non-SetReply responses are buffered
+     * Values for keys in the data storage can be retrieved with a Get package, or monitored with a SetNotify package.
+     * If `want_reply` is set, waits for the SetReply for this key and buffers other responses;
+     * otherwise returns as soon as the request is sent.
      */
     pub async fn set(
         &mut self,
@@ -307,22 +1024,338 @@ where
         default: serde_json::Value,
         want_reply: bool,
         operations: Vec<DataStorageOperation>,
-    ) -> Result<SetReply, ArchipelagoError> {
-        self.send(ClientMessage::Set(Set {
+    ) -> Result<Option<SetReply>, ArchipelagoError> {
+        self.send_set(Set {
             key,
             default,
             want_reply,
             operations,
-        }))
-        .await?;
-        while let Some(response) = self.recv().await? {
-            match response {
-                ServerMessage::SetReply(items) => return Ok(items),
-                resp => self.message_buffer.push(resp),
-            }
+            extra: serde_json::Map::new(),
+        })
+        .await
+    }
+
+    /**
+     * Read the current value of a typed data storage key.
+     *
+     * Returns `None` if the key doesn't exist on the server.
+     */
+    pub async fn read<T>(&mut self, key: &DataStorage<T>) -> Result<Option<T>, ArchipelagoError>
+    where
+        T: for<'a> serde::de::Deserialize<'a>,
+    {
+        let mut retrieved = self.get(vec![key.key().to_string()]).await?;
+        match retrieved
+            .keys
+            .get_mut(key.key())
+            .map(serde_json::Value::take)
+        {
+            None | Some(serde_json::Value::Null) => Ok(None),
+            Some(value) => storage::decode_value(value).map(Some),
+        }
+    }
+
+    /**
+     * Read the hints relevant to a slot on a team, whether it's finding or receiving the item.
+     */
+    pub async fn read_hints(
+        &mut self,
+        team: TeamId,
+        slot: SlotId,
+    ) -> Result<Vec<Hint>, ArchipelagoError> {
+        Ok(self
+            .read(&DataStorage::hints(team, slot))
+            .await?
+            .unwrap_or_default())
+    }
+
+    /**
+     * Read the slot data of any slot, decoded as `T`.
+     *
+     * Returns `None` if the slot doesn't exist.
+     */
+    pub async fn read_slot_data<T>(&mut self, slot: SlotId) -> Result<Option<T>, ArchipelagoError>
+    where
+        T: for<'a> serde::de::Deserialize<'a>,
+    {
+        self.read(&DataStorage::slot_data(slot)).await
+    }
+
+    /**
+     * Read the item name groups of a game, mapping each group name to the names of its items.
+     */
+    pub async fn read_item_name_groups(
+        &mut self,
+        game: &str,
+    ) -> Result<NameGroups, ArchipelagoError> {
+        Ok(self
+            .read(&DataStorage::item_name_groups(game))
+            .await?
+            .unwrap_or_default())
+    }
+
+    /**
+     * Read the location name groups of a game, mapping each group name to the names of its
+     * locations.
+     */
+    pub async fn read_location_name_groups(
+        &mut self,
+        game: &str,
+    ) -> Result<NameGroups, ArchipelagoError> {
+        Ok(self
+            .read(&DataStorage::location_name_groups(game))
+            .await?
+            .unwrap_or_default())
+    }
+
+    /**
+     * Read the current client status of a slot on a team.
+     */
+    pub async fn read_client_status(
+        &mut self,
+        team: TeamId,
+        slot: SlotId,
+    ) -> Result<ClientStatus, ArchipelagoError> {
+        Ok(self
+            .read(&DataStorage::client_status(team, slot))
+            .await?
+            .unwrap_or(ClientStatus::ClientUnknown))
+    }
+
+    /**
+     * Read whether the room is in race mode.
+     */
+    pub async fn read_race_mode(&mut self) -> Result<bool, ArchipelagoError> {
+        Ok(self.read(&DataStorage::race_mode()).await?.unwrap_or(0) != 0)
+    }
+
+    /**
+     * Send a typed data storage write without waiting for the server to apply it.
+     */
+    pub async fn write<T>(&mut self, set: SetBuilder<T>) -> Result<(), ArchipelagoError>
+    where
+        T: serde::Serialize,
+    {
+        self.send_set(set.build(false)?).await?;
+        Ok(())
+    }
+
+    /**
+     * Send a typed data storage write and wait for the server's SetReply, which contains the
+     * key's value both before and after the write. Non-SetReply responses are buffered
+     */
+    pub async fn write_with_reply<T>(
+        &mut self,
+        set: SetBuilder<T>,
+    ) -> Result<KeyChange<T>, ArchipelagoError>
+    where
+        T: serde::Serialize + for<'a> serde::de::Deserialize<'a>,
+    {
+        let reply = self
+            .send_set(set.build(true)?)
+            .await?
+            .ok_or(ArchipelagoError::ConnectionClosed)?;
+        storage::decode_change(reply)
+    }
+
+    /**
+     * Ask the server to notify this client whenever any of the given data storage keys change.
+     *
+     * The keys' current values are fetched with a Get so that the storage mirror is seeded
+     * immediately; afterwards the mirror is kept current as SetReply notifications are received.
+     * Keys that are already watched are not requested again.
+     */
+    pub async fn watch(&mut self, keys: Vec<String>) -> Result<(), ArchipelagoError> {
+        let keys = self.storage.watch(keys);
+        if keys.is_empty() {
+            return Ok(());
+        }
+        self.send(ClientMessage::SetNotify(SetNotify { keys: keys.clone() }))
+            .await?;
+        self.get(keys).await?;
+
+        Ok(())
+    }
+
+    /**
+     * Watch a data storage key and return a stream of its changes, decoded as `T`.
+     *
+     * The subscription only yields changes while this client (or its receiving half) is
+     * receiving messages. The latest value is also available from `storage_mirror`.
+     */
+    pub async fn subscribe<T>(&mut self, key: &str) -> Result<Subscription<T>, ArchipelagoError>
+    where
+        T: for<'a> serde::de::Deserialize<'a>,
+    {
+        self.watch(vec![key.to_string()]).await?;
+        Ok(self.storage.subscribe(key))
+    }
+
+    /**
+     * Watch a typed data storage key and return a stream of its changes.
+     *
+     * See `subscribe` for details.
+     */
+    pub async fn subscribe_to<T>(
+        &mut self,
+        key: &DataStorage<T>,
+    ) -> Result<Subscription<T>, ArchipelagoError>
+    where
+        T: for<'a> serde::de::Deserialize<'a>,
+    {
+        self.subscribe(key.key()).await
+    }
+
+    /**
+     * Start keeping `hint_book` current with the connected slot's hints.
+     *
+     * Fetches the current hints and watches the slot's hint key for changes. Must be called
+     * after `connect`.
+     */
+    pub async fn track_hints(&mut self) -> Result<&HintBook, ArchipelagoError> {
+        let connection = self
+            .connection
+            .as_ref()
+            .ok_or(ArchipelagoError::NotConnected)?;
+        let mut book = HintBook::new(connection.team, connection.slot);
+        let key = book.key().to_string();
+        // If the key is already watched, `watch` won't fetch it again, so
+        // start from the mirrored value.
+        if let Some(value) = self.storage.get(&key) {
+            book.replace(value);
+        }
+        self.hint_book = Some(book);
+        self.watch(vec![key]).await?;
+
+        Ok(self.hint_book.as_ref().unwrap())
+    }
+
+    /**
+     * Add energy to the connected team's EnergyLink pool without waiting for the server.
+     */
+    pub async fn deposit_energy(&mut self, amount: f64) -> Result<(), ArchipelagoError> {
+        let energy_link = self.energy_link()?;
+        self.write(energy_link.deposit(amount)).await
+    }
+
+    /**
+     * Take up to `amount` energy from the connected team's EnergyLink pool.
+     *
+     * Returns the energy actually taken, which is less than `amount` if the pool didn't hold
+     * enough. Non-SetReply responses are buffered
+     */
+    pub async fn withdraw_energy(&mut self, amount: f64) -> Result<f64, ArchipelagoError> {
+        let energy_link = self.energy_link()?;
+        let reply = self.write_with_reply(energy_link.withdraw(amount)).await?;
+        Ok(EnergyLink::withdrawn(&reply))
+    }
+
+    /**
+     * Watch the connected team's EnergyLink pool so that `energy_balance` stays current.
+     */
+    pub async fn watch_energy(&mut self) -> Result<(), ArchipelagoError> {
+        let energy_link = self.energy_link()?;
+        self.watch(vec![energy_link.storage().key().to_string()])
+            .await
+    }
+
+    /// Returns the latest known energy in the connected team's EnergyLink
+    /// pool, or `None` if it isn't being watched with `watch_energy`.
+    pub fn energy_balance(&self) -> Option<f64> {
+        let energy_link = self.energy_link().ok()?;
+        match self.storage.get(energy_link.storage().key())? {
+            serde_json::Value::Null => Some(0.0),
+            value => value.as_f64(),
         }
+    }
+
+    /**
+     * Returns the gifting participant for the connected slot, used to create gifts.
+     */
+    pub fn gifting(&self) -> Result<Gifting, ArchipelagoError> {
+        let connection = self
+            .connection
+            .as_ref()
+            .ok_or(ArchipelagoError::NotConnected)?;
+        Ok(Gifting::new(connection.team, connection.slot))
+    }
 
-        Err(ArchipelagoError::ConnectionClosed)
+    /**
+     * Open the connected slot's gift box and advertise which gifts it accepts.
+     *
+     * The gift box is also watched, so pending gifts show up in `storage_mirror`.
+     */
+    pub async fn open_gift_box(
+        &mut self,
+        accepts_any_gift: bool,
+        desired_traits: Vec<String>,
+    ) -> Result<(), ArchipelagoError> {
+        let gifting = self.gifting()?;
+        let (motherbox, gift_box) = gifting.open(accepts_any_gift, desired_traits);
+        self.write(gift_box).await?;
+        self.write(motherbox).await?;
+        self.watch(vec![gifting.own_gift_box().key().to_string()])
+            .await
+    }
+
+    /**
+     * Close the connected slot's gift box to new gifts.
+     */
+    pub async fn close_gift_box(&mut self) -> Result<(), ArchipelagoError> {
+        let gifting = self.gifting()?;
+        self.write(gifting.close()).await
+    }
+
+    /**
+     * Read the gift box advertised by a slot on a team, if it has one.
+     */
+    pub async fn gift_box_info(
+        &mut self,
+        team: TeamId,
+        slot: SlotId,
+    ) -> Result<Option<GiftBoxInfo>, ArchipelagoError> {
+        let mut motherbox = self
+            .read(&Gifting::motherbox(team))
+            .await?
+            .unwrap_or_default();
+        Ok(motherbox.remove(&slot.to_string()))
+    }
+
+    /**
+     * Send a gift to its receiver's gift box.
+     *
+     * Returns false without sending if the receiver's gift box is closed or doesn't accept the
+     * gift's traits.
+     */
+    pub async fn send_gift(&mut self, gift: &Gift) -> Result<bool, ArchipelagoError> {
+        let accepted = self
+            .gift_box_info(gift.receiver_team, gift.receiver_slot)
+            .await?
+            .is_some_and(|info| info.accepts(&gift.traits));
+        if accepted {
+            self.write(Gifting::send(gift)).await?;
+        }
+
+        Ok(accepted)
+    }
+
+    /**
+     * Take every gift waiting in the connected slot's gift box, removing them from the box.
+     */
+    pub async fn take_gifts(&mut self) -> Result<Vec<Gift>, ArchipelagoError> {
+        let gifting = self.gifting()?;
+        let gifts: Vec<Gift> = self
+            .read(&gifting.own_gift_box())
+            .await?
+            .unwrap_or_default()
+            .into_values()
+            .collect();
+        if !gifts.is_empty() {
+            self.write(gifting.remove(gifts.iter().map(|gift| gift.id.as_str())))
+                .await?;
+        }
+
+        Ok(gifts)
     }
 
     /**
@@ -331,6 +1364,10 @@ where
      * This removes access to a few convenience methods (like `get` or `set`) because it's
      * there's now extra coordination required to match a read and write, but it brings
      * the benefits of allowing simultaneous reading and writing.
+     *
+     * The split halves don't track items, hints or queued location checks, since keeping them up
+     * to date needs both halves, so that state is dropped along with the inventory, connection
+     * info and name lookup. Use `try_split` to refuse to split while any of them are tracked.
      */
     pub fn split(self) -> (ArchipelagoClientSender, ArchipelagoClientReceiver<S>) {
         let Self {
@@ -338,6 +1375,8 @@ where
             room_info,
             message_buffer,
             data_package,
+            storage,
+            ..
         } = self;
         let (send, recv) = ws.split();
         (
@@ -347,10 +1386,281 @@ where
                 room_info,
                 message_buffer,
                 data_package,
+                storage,
             },
         )
     }
 
+    /**
+     * Split the client like `split`, unless it's tracking items, hints or queued location checks,
+     * which the split halves can't keep up to date.
+     */
+    pub fn try_split(
+        self,
+    ) -> Result<(ArchipelagoClientSender, ArchipelagoClientReceiver<S>), ArchipelagoError> {
+        if self.item_delivery.is_some() {
+            return Err(ArchipelagoError::SplitWhileTracking("items"));
+        }
+        if self.hint_book.is_some() {
+            return Err(ArchipelagoError::SplitWhileTracking("hints"));
+        }
+        if !self.outbox.is_empty() {
+            return Err(ArchipelagoError::SplitWhileTracking("location checks"));
+        }
+        Ok(self.split())
+    }
+
+    fn energy_link(&self) -> Result<EnergyLink, ArchipelagoError> {
+        let connection = self
+            .connection
+            .as_ref()
+            .ok_or(ArchipelagoError::NotConnected)?;
+        Ok(EnergyLink::new(connection.team))
+    }
+
+    /// Sends a Set and, if it wants a reply, waits for the SetReply for the
+    /// same key while buffering other responses.
+    async fn send_set(&mut self, mut set: Set) -> Result<Option<SetReply>, ArchipelagoError> {
+        if !set.want_reply {
+            self.send(ClientMessage::Set(set)).await?;
+            return Ok(None);
+        }
+        // Watched keys also get a SetReply for every other client's write,
+        // so the reply is told apart by an ID the server copies into it.
+        self.sets_sent += 1;
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos());
+        let id = serde_json::Value::from(format!("{nanos:x}-{}", self.sets_sent));
+        set.extra.insert(REPLY_ID_FIELD.to_string(), id.clone());
+        let key = set.key.clone();
+        self.send(ClientMessage::Set(set)).await?;
+        self.recv_until(|response| match response {
+            ServerMessage::SetReply(reply)
+                if reply.key == key && reply.extra.get(REPLY_ID_FIELD) == Some(&id) =>
+            {
+                ControlFlow::Break(Some(reply))
+            }
+            resp => ControlFlow::Continue(resp),
+        })
+        .await
+    }
+
+    /// Updates the client's local state from a message that was just
+    /// received from the server.
+    fn handle_incoming(&mut self, message: &ServerMessage<S>) {
+        self.storage.handle_message(message);
+        if let Some(book) = &mut self.hint_book {
+            book.handle_message(message);
+        }
+        match message {
+            ServerMessage::DataPackage(package) => {
+                let mut refreshed = Vec::new();
+                for (game, data) in &package.data.games {
+                    let refreshing = self.refreshing_games.remove(game);
+                    if refreshing
+                        && (data.item_name_groups.is_none() || data.location_name_groups.is_none())
+                    {
+                        // The name groups were requested along with the
+                        // game, which is merged once they arrive.
+                        self.awaiting_groups.insert(game.clone(), data.clone());
+                    } else if self.merge_game(game.clone(), data.clone()) && refreshing {
+                        refreshed.push(game.clone());
+                    }
+                }
+                self.report_refreshed(refreshed);
+            }
+            ServerMessage::Retrieved(retrieved) => {
+                let ready: Vec<String> = self
+                    .awaiting_groups
+                    .keys()
+                    .filter(|game| {
+                        retrieved_groups(retrieved, &DataStorage::item_name_groups(game)).is_some()
+                            && retrieved_groups(retrieved, &DataStorage::location_name_groups(game))
+                                .is_some()
+                    })
+                    .cloned()
+                    .collect();
+                let mut refreshed = Vec::new();
+                for game in ready {
+                    let Some(mut data) = self.awaiting_groups.remove(&game) else {
+                        continue;
+                    };
+                    data.item_name_groups =
+                        retrieved_groups(retrieved, &DataStorage::item_name_groups(&game));
+                    data.location_name_groups =
+                        retrieved_groups(retrieved, &DataStorage::location_name_groups(&game));
+                    if self.merge_game(game.clone(), data) {
+                        refreshed.push(game);
+                    }
+                }
+                self.report_refreshed(refreshed);
+            }
+            ServerMessage::Connected(connected) => {
+                let connection = ConnectionInfo::new(connected);
+                // Queued checks of locations that are already checked, or
+                // that aren't the slot's, would never be acknowledged.
+                self.outbox
+                    .retain(|location| connection.missing_locations.contains(location));
+                self.connection = Some(connection);
+                self.rebuild_lookup();
+                self.resend_needed = !self.outbox.is_empty();
+            }
+            ServerMessage::ReceivedItems(items) => {
+                if !self.inventory.receive(items) {
+                    self.sync_needed = true;
+                }
+                if let Some(delivery) = &mut self.item_delivery {
+                    match delivery.receive(items) {
+                        ReceiveOutcome::Queued(_) => {}
+                        ReceiveOutcome::Gap => self.sync_needed = true,
+                        ReceiveOutcome::Reset => self.events.push(ClientEvent::ItemsReset {
+                            received: items.items.len() as i64,
+                            applied: delivery.applied_index(),
+                        }),
+                    }
+                }
+            }
+            ServerMessage::RoomUpdate(update) => {
+                if let (Some(package), Some(checksums)) =
+                    (&self.data_package, &update.datapackage_checksums)
+                {
+                    // Only games whose checksum changed need fetching again,
+                    // and only if the client is using the data package.
+                    let changed = checksums.iter().filter(|(game, checksum)| {
+                        self.room_info.datapackage_checksums.get(*game) != Some(*checksum)
+                            && package
+                                .games
+                                .get(*game)
+                                .is_none_or(|data| &data.checksum != *checksum)
+                    });
+                    self.stale_games
+                        .extend(changed.map(|(game, _)| game.clone()));
+                }
+                self.room_info.apply_update(update);
+                if let Some(checked_locations) = &update.checked_locations {
+                    self.outbox.acknowledge(checked_locations);
+                }
+                if let Some(connection) = &mut self.connection {
+                    connection.apply_update(update);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Receives messages until [select] accepts one by returning `Break`.
+    ///
+    /// The messages it hands back are held aside while waiting, since
+    /// putting them straight back into the buffer would make `recv` return
+    /// them again, and are returned by `recv` afterwards in the order they
+    /// arrived.
+    async fn recv_until<T>(
+        &mut self,
+        mut select: impl FnMut(ServerMessage<S>) -> ControlFlow<T, ServerMessage<S>>,
+    ) -> Result<T, ArchipelagoError> {
+        let mut held = Vec::new();
+        let result = loop {
+            match self.recv().await {
+                Ok(Some(message)) => match select(message) {
+                    ControlFlow::Break(value) => break Ok(value),
+                    ControlFlow::Continue(message) => held.push(message),
+                },
+                Ok(None) => break Err(ArchipelagoError::ConnectionClosed),
+                Err(error) => break Err(error),
+            }
+        };
+        // The buffer is popped from the back, and the held messages arrived
+        // before any still in it.
+        self.message_buffer.extend(held.into_iter().rev());
+        result
+    }
+
+    /// Requests the Data Package of the given games, which must be in the
+    /// room, and waits for one that includes all of them, buffering other
+    /// responses. The games are merged into `data_package` as the response
+    /// is received.
+    async fn fetch_data_package(
+        &mut self,
+        games: Vec<String>,
+    ) -> Result<DataPackageObject, ArchipelagoError> {
+        self.send(ClientMessage::GetDataPackage(GetDataPackage {
+            games: Some(games.clone()),
+        }))
+        .await?;
+        // `recv` fetches games whose checksums changed, and the reply to
+        // that can arrive first.
+        self.recv_until(|response| match response {
+            ServerMessage::DataPackage(package)
+                if games
+                    .iter()
+                    .all(|game| package.data.games.contains_key(game)) =>
+            {
+                ControlFlow::Break(package.data)
+            }
+            resp => ControlFlow::Continue(resp),
+        })
+        .await
+    }
+
+    /// Merges [data] for [game] into `data_package`, verifying it and writing
+    /// it to the cache if it has the name groups its checksum covers, or
+    /// marking it unverified if it doesn't. Returns whether it was merged.
+    fn merge_game(&mut self, game: String, data: GameData) -> bool {
+        if data.item_name_groups.is_none() || data.location_name_groups.is_none() {
+            self.unverified_games.insert(game.clone());
+        } else {
+            self.unverified_games.remove(&game);
+            let actual = checksum::compute(&data);
+            if actual != data.checksum {
+                if let Some(package) = &mut self.data_package {
+                    package.games.remove(&game);
+                }
+                self.rebuild_lookup();
+                self.events.push(ClientEvent::ChecksumMismatch {
+                    game,
+                    expected: data.checksum,
+                    actual,
+                });
+                return false;
+            }
+            if let Some(cache) = &self.cache {
+                // A game that can't be cached is just fetched again next
+                // time.
+                let _ = cache.store(&game, &data);
+            }
+        }
+        self.merge_data_package(HashMap::from([(game, data)]));
+        true
+    }
+
+    fn report_refreshed(&mut self, mut games: Vec<String>) {
+        if !games.is_empty() {
+            games.sort();
+            self.events
+                .push(ClientEvent::DataPackageRefreshed { games });
+        }
+    }
+
+    fn merge_data_package(&mut self, games: HashMap<String, GameData>) {
+        self.data_package
+            .get_or_insert_with(|| DataPackageObject {
+                games: HashMap::new(),
+            })
+            .games
+            .extend(games);
+        self.rebuild_lookup();
+    }
+
+    fn rebuild_lookup(&mut self) {
+        self.lookup = match (&self.data_package, &self.connection) {
+            (Some(package), Some(connection)) => {
+                Some(NameLookup::new(package, &connection.slot_info))
+            }
+            _ => None,
+        };
+    }
+
     /// Returns an illegal response error indicating the [expected] response
     /// type and the actual type of [received].
     fn illegal_response(expected: &'static str, received: ServerMessage<S>) -> ArchipelagoError {
@@ -380,41 +1690,85 @@ impl ArchipelagoClientSender {
         Ok(())
     }
 
+    pub async fn connect_update(
+        &mut self,
+        items_handling: ItemsHandlingFlags,
+        tags: Vec<Tag>,
+    ) -> Result<(), ArchipelagoError> {
+        if !items_handling.is_valid() {
+            return Err(ArchipelagoError::InvalidItemsHandling(items_handling));
+        }
+        self.send(ClientMessage::ConnectUpdate(ConnectUpdate {
+            items_handling,
+            tags,
+        }))
+        .await
+    }
+
     pub async fn say(&mut self, message: &str) -> Result<(), ArchipelagoError> {
-        Ok(self
-            .send(ClientMessage::Say(Say {
-                text: message.to_string(),
-            }))
-            .await?)
+        self.send(ClientMessage::Say(Say {
+            text: message.to_string(),
+        }))
+        .await
     }
 
-    pub async fn location_checks(&mut self, locations: Vec<i64>) -> Result<(), ArchipelagoError> {
-        Ok(self
-            .send(ClientMessage::LocationChecks(LocationChecks { locations }))
-            .await?)
+    pub async fn location_checks(
+        &mut self,
+        locations: Vec<LocationId>,
+    ) -> Result<(), ArchipelagoError> {
+        self.send(ClientMessage::LocationChecks(LocationChecks { locations }))
+            .await
     }
 
     pub async fn status_update(&mut self, status: ClientStatus) -> Result<(), ArchipelagoError> {
-        Ok(self
-            .send(ClientMessage::StatusUpdate(StatusUpdate { status }))
-            .await?)
+        self.send(ClientMessage::StatusUpdate(StatusUpdate { status }))
+            .await
     }
 
-    pub async fn bounce(
+    pub async fn bounce<T>(
         &mut self,
         games: Option<Vec<String>>,
-        slots: Option<Vec<String>>,
-        tags: Option<Vec<String>>,
-        data: serde_json::Value,
-    ) -> Result<(), ArchipelagoError> {
-        Ok(self
-            .send(ClientMessage::Bounce(Bounce {
-                games,
-                slots,
-                tags,
-                data,
-            }))
-            .await?)
+        slots: Option<Vec<SlotId>>,
+        tags: Option<Vec<Tag>>,
+        data: T,
+    ) -> Result<(), ArchipelagoError>
+    where
+        T: serde::Serialize,
+    {
+        let bounce = Bounce {
+            games,
+            slots,
+            tags,
+            data,
+        };
+        self.send(ClientMessage::Bounce(bounce.into_untyped()?))
+            .await
+    }
+
+    pub async fn send_link<L>(
+        &mut self,
+        link: &L,
+        payload: &L::Payload,
+    ) -> Result<(), ArchipelagoError>
+    where
+        L: Link,
+    {
+        self.send(ClientMessage::Bounce(link.bounce(payload)?))
+            .await
+    }
+
+    pub async fn send_death(
+        &mut self,
+        death_link: &mut DeathLink,
+        cause: Option<String>,
+    ) -> Result<bool, ArchipelagoError> {
+        match death_link.record_death(cause) {
+            Some(bounce) => {
+                self.send(ClientMessage::Bounce(bounce)).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
     }
 }
 
@@ -433,6 +1787,7 @@ where
     room_info: RoomInfo,
     message_buffer: Vec<ServerMessage<S>>,
     data_package: Option<DataPackageObject>,
+    storage: StorageMirror,
 }
 
 impl<S> ArchipelagoClientReceiver<S>
@@ -446,6 +1801,9 @@ where
         let messages = recv_messages(&mut self.ws).await;
         if let Some(result) = messages {
             let mut messages = result?;
+            for message in &messages {
+                self.storage.handle_message(message);
+            }
             messages.reverse();
             let first = messages.pop();
             self.message_buffer = messages;
@@ -462,6 +1820,20 @@ where
     pub fn data_package(&self) -> Option<&DataPackageObject> {
         self.data_package.as_ref()
     }
+
+    pub fn storage_mirror(&self) -> &StorageMirror {
+        &self.storage
+    }
+}
+
+/// Returns the name groups stored under [key] in [retrieved], or `None` if
+/// it doesn't include the key. Groups that can't be decoded are returned
+/// empty, and so fail verification.
+fn retrieved_groups(retrieved: &Retrieved, key: &DataStorage<NameGroups>) -> Option<NameGroups> {
+    match retrieved.keys.get(key.key())? {
+        serde_json::Value::Null => Some(NameGroups::new()),
+        value => Some(storage::decode_value(value.clone()).unwrap_or_default()),
+    }
 }
 
 async fn recv_messages<S>(
@@ -0,0 +1,300 @@
+//! Generating Rust enums for a game's item and location IDs, for use from a
+//! build script.
+//!
+//! ```no_run
+//! // build.rs
+//! use std::path::PathBuf;
+//!
+//! use archipelago_rs::codegen::EnumGenerator;
+//!
+//! fn main() {
+//!     let out = PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("ids.rs");
+//!     EnumGenerator::from_json_file("my_game_data.json")
+//!         .unwrap()
+//!         .write(out)
+//!         .unwrap();
+//!     println!("cargo:rerun-if-changed=my_game_data.json");
+//! }
+//! ```
+//!
+//! The generated file is then included with
+//! `include!(concat!(env!("OUT_DIR"), "/ids.rs"));`.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use indexmap::IndexMap;
+
+use crate::client::ArchipelagoError;
+use crate::protocol::GameData;
+
+/// Generates an enum each for the items and locations of a game's
+/// [GameData], with a variant per name.
+///
+/// Each enum implements `TryFrom<i64>` and `TryFrom` the matching `ItemId`
+/// or `LocationId` (failing with the unknown ID), converts back into both,
+/// and has `id`, `name` and `from_name` methods and an
+/// `ALL` constant listing every variant in ID order. Variant names are the
+/// item and location names in PascalCase, with a number appended when two
+/// names would collide. When several names share an ID, converting the ID
+/// returns the first of them in alphabetical order.
+#[derive(Debug, Clone)]
+pub struct EnumGenerator {
+    data: GameData,
+    item_enum: String,
+    location_enum: String,
+}
+
+impl EnumGenerator {
+    /// Creates a generator for [data] that names its enums `Item` and
+    /// `Location`.
+    pub fn new(data: GameData) -> EnumGenerator {
+        EnumGenerator {
+            data,
+            item_enum: "Item".to_string(),
+            location_enum: "Location".to_string(),
+        }
+    }
+
+    /// Creates a generator for the [GameData] stored as JSON in [path], such
+    /// as one game from a dumped data package.
+    pub fn from_json_file(path: impl AsRef<Path>) -> Result<EnumGenerator, ArchipelagoError> {
+        let json = fs::read_to_string(path)?;
+        let data = serde_json::from_str(&json)
+            .map_err(|error| ArchipelagoError::FailedDeserialize { json, error })?;
+        Ok(EnumGenerator::new(data))
+    }
+
+    /// Names the generated item enum [name] instead of `Item`.
+    pub fn with_item_enum(mut self, name: impl Into<String>) -> EnumGenerator {
+        self.item_enum = name.into();
+        self
+    }
+
+    /// Names the generated location enum [name] instead of `Location`.
+    pub fn with_location_enum(mut self, name: impl Into<String>) -> EnumGenerator {
+        self.location_enum = name.into();
+        self
+    }
+
+    /// Returns the Rust source of both enums.
+    pub fn generate(&self) -> String {
+        let mut out = String::from("// Generated by archipelago_rs::codegen. Do not edit.\n");
+        generate_enum(
+            &mut out,
+            &self.item_enum,
+            "ItemId",
+            &self.data.item_name_to_id,
+        );
+        generate_enum(
+            &mut out,
+            &self.location_enum,
+            "LocationId",
+            &self.data.location_name_to_id,
+        );
+        out
+    }
+
+    /// Writes the Rust source of both enums to [path], leaving the file
+    /// untouched if it's already up to date so dependents aren't rebuilt.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), ArchipelagoError> {
+        let source = self.generate();
+        if fs::read_to_string(&path).ok().as_deref() != Some(source.as_str()) {
+            fs::write(path, source)?;
+        }
+        Ok(())
+    }
+}
+
+fn generate_enum<T>(out: &mut String, name: &str, id_type: &str, ids: &IndexMap<String, T>)
+where
+    T: Copy + Into<i64>,
+{
+    let mut entries: Vec<(&str, i64)> = ids
+        .iter()
+        .map(|(name, id)| (name.as_str(), (*id).into()))
+        .collect();
+    entries.sort_by_key(|(name, id)| (*id, *name));
+
+    let mut used = HashSet::new();
+    let variants: Vec<(String, &str, i64)> = entries
+        .into_iter()
+        .map(|(name, id)| {
+            let base = variant_name(name);
+            let mut variant = base.clone();
+            let mut suffix = 2;
+            while !used.insert(variant.clone()) {
+                variant = format!("{base}{suffix}");
+                suffix += 1;
+            }
+            (variant, name, id)
+        })
+        .collect();
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]");
+    let _ = writeln!(out, "pub enum {name} {{");
+    for (variant, item, id) in &variants {
+        let _ = writeln!(out, "    /// {} ({id})", item.escape_debug());
+        let _ = writeln!(out, "    {variant},");
+    }
+    let _ = writeln!(out, "}}");
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "impl {name} {{");
+    let _ = writeln!(out, "    pub const ALL: &[{name}] = &[");
+    for (variant, _, _) in &variants {
+        let _ = writeln!(out, "        {name}::{variant},");
+    }
+    let _ = writeln!(out, "    ];");
+    write_match(out, "id", "i64", name, &variants, |_, id| id.to_string());
+    write_match(out, "name", "&'static str", name, &variants, |item, _| {
+        format!("{item:?}")
+    });
+    let _ = writeln!(out);
+    let _ = writeln!(out, "    pub fn from_name(name: &str) -> Option<{name}> {{");
+    let _ = writeln!(out, "        match name {{");
+    for (variant, item, _) in &variants {
+        let _ = writeln!(out, "            {item:?} => Some({name}::{variant}),");
+    }
+    let _ = writeln!(out, "            _ => None,");
+    let _ = writeln!(out, "        }}");
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out, "}}");
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "impl TryFrom<i64> for {name} {{");
+    let _ = writeln!(out, "    type Error = i64;");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "    fn try_from(id: i64) -> Result<{name}, i64> {{");
+    let _ = writeln!(out, "        match id {{");
+    let mut previous = None;
+    for (variant, _, id) in &variants {
+        // Variants are sorted by ID, and only the first with each ID is
+        // reachable.
+        if previous.replace(*id) != Some(*id) {
+            let _ = writeln!(out, "            {id} => Ok({name}::{variant}),");
+        }
+    }
+    let _ = writeln!(out, "            _ => Err(id),");
+    let _ = writeln!(out, "        }}");
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out, "}}");
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "impl From<{name}> for i64 {{");
+    let _ = writeln!(out, "    fn from(value: {name}) -> i64 {{");
+    let _ = writeln!(out, "        value.id()");
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out, "}}");
+
+    let id_type = format!("archipelago_rs::protocol::{id_type}");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "impl TryFrom<{id_type}> for {name} {{");
+    let _ = writeln!(out, "    type Error = {id_type};");
+    let _ = writeln!(out);
+    let _ = writeln!(
+        out,
+        "    fn try_from(id: {id_type}) -> Result<{name}, {id_type}> {{"
+    );
+    let _ = writeln!(out, "        {name}::try_from(id.0).map_err({id_type})");
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out, "}}");
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "impl From<{name}> for {id_type} {{");
+    let _ = writeln!(out, "    fn from(value: {name}) -> {id_type} {{");
+    let _ = writeln!(out, "        {id_type}(value.id())");
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out, "}}");
+}
+
+/// Writes a method named [method] that maps each variant to a value of type
+/// [ty] produced by [value].
+fn write_match(
+    out: &mut String,
+    method: &str,
+    ty: &str,
+    name: &str,
+    variants: &[(String, &str, i64)],
+    value: impl Fn(&str, i64) -> String,
+) {
+    let _ = writeln!(out);
+    let _ = writeln!(out, "    pub fn {method}(self) -> {ty} {{");
+    let _ = writeln!(out, "        match self {{");
+    for (variant, item, id) in variants {
+        let _ = writeln!(
+            out,
+            "            {name}::{variant} => {},",
+            value(item, *id)
+        );
+    }
+    let _ = writeln!(out, "        }}");
+    let _ = writeln!(out, "    }}");
+}
+
+/// Turns [name] into a PascalCase identifier, such as `ProgressiveSword` for
+/// "Progressive Sword".
+fn variant_name(name: &str) -> String {
+    let mut variant: String = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().unwrap_or_default().to_ascii_uppercase();
+            std::iter::once(first).chain(chars).collect::<String>()
+        })
+        .collect();
+    if variant.is_empty() {
+        variant.push_str("Unnamed");
+    } else if variant.starts_with(|c: char| c.is_ascii_digit()) {
+        variant.insert(0, 'N');
+    } else if variant == "Self" {
+        variant.push_str("Name");
+    }
+    variant
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate(items: &str) -> String {
+        let data: GameData = serde_json::from_str(&format!(
+            r#"{{"item_name_to_id": {items}, "location_name_to_id": {{}}, "checksum": ""}}"#
+        ))
+        .unwrap();
+        EnumGenerator::new(data).generate()
+    }
+
+    #[test]
+    fn names_variants_in_pascal_case() {
+        assert_eq!(variant_name("Progressive Sword"), "ProgressiveSword");
+        assert_eq!(variant_name("boss key (tower)"), "BossKeyTower");
+        assert_eq!(variant_name("3 Hearts"), "N3Hearts");
+        assert_eq!(variant_name("Self"), "SelfName");
+        assert_eq!(variant_name("!!"), "Unnamed");
+    }
+
+    #[test]
+    fn generates_unique_variants() {
+        let source = generate(r#"{"Sword": 1, "sword": 2, "3 Hearts": 3, "Self": 4}"#);
+        assert!(source.contains("    Sword,\n"));
+        assert!(source.contains("    Sword2,\n"));
+        assert!(source.contains("    N3Hearts,\n"));
+        assert!(source.contains("    SelfName,\n"));
+        assert!(source.contains("\"sword\" => Some(Item::Sword2),"));
+        assert!(source.contains("pub const ALL: &[Item] = &["));
+    }
+
+    #[test]
+    fn converts_shared_ids_to_the_first_name() {
+        let source = generate(r#"{"Bow": 1, "Arrow": 1}"#);
+        assert!(source.contains("Item::Arrow => 1,"));
+        assert!(source.contains("Item::Bow => 1,"));
+        assert_eq!(source.matches("            1 => Ok(").count(), 1);
+        assert!(source.contains("            1 => Ok(Item::Arrow),"));
+    }
+}
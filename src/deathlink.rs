@@ -0,0 +1,111 @@
+//! Support for DeathLink, which kills every participating player when any one
+//! of them dies.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::links::{self, Link};
+use crate::protocol::{Bounce, Tag};
+
+/// The number of our own recent deaths remembered to recognize their echoes.
+const REMEMBERED_DEATHS: usize = 8;
+
+/// A death, as carried in the data of a DeathLink Bounce.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeathLinkEvent {
+    /// The Unix timestamp of the death, in seconds.
+    pub time: f64,
+
+    /// The name of the player who died.
+    pub source: String,
+
+    /// A human-readable description of how the player died.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cause: Option<String>,
+}
+
+/// The state of a client participating in DeathLink.
+///
+/// The client must also connect with [Tag::DeathLink] to receive other
+/// players' deaths.
+#[derive(Debug, Clone)]
+pub struct DeathLink {
+    source: String,
+    amnesty: u32,
+    forgiven: u32,
+    sent: VecDeque<f64>,
+}
+
+impl DeathLink {
+    /// Creates a DeathLink participant that reports deaths as [source],
+    /// usually the connected slot's name.
+    pub fn new(source: impl Into<String>) -> DeathLink {
+        DeathLink {
+            source: source.into(),
+            amnesty: 0,
+            forgiven: 0,
+            sent: VecDeque::new(),
+        }
+    }
+
+    /// Forgives [amnesty] local deaths before each one that's sent to other
+    /// players. With an amnesty of 2, only every third death is sent.
+    pub fn with_amnesty(mut self, amnesty: u32) -> DeathLink {
+        self.amnesty = amnesty;
+        self
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns how many more local deaths will be forgiven before one is sent.
+    pub fn remaining_amnesty(&self) -> u32 {
+        self.amnesty.saturating_sub(self.forgiven)
+    }
+
+    /// Records a local death, returning the Bounce to send if it isn't
+    /// forgiven by the amnesty threshold.
+    ///
+    /// Deaths caused by receiving another player's death shouldn't be
+    /// recorded, or they'd bounce back to everyone else.
+    pub fn record_death(&mut self, cause: Option<String>) -> Option<Bounce> {
+        if self.forgiven < self.amnesty {
+            self.forgiven += 1;
+            return None;
+        }
+        self.forgiven = 0;
+
+        let time = links::unix_time();
+        if self.sent.len() == REMEMBERED_DEATHS {
+            self.sent.pop_front();
+        }
+        self.sent.push_back(time);
+
+        let event = DeathLinkEvent {
+            time,
+            source: self.source.clone(),
+            cause,
+        };
+        Some(
+            self.bounce(&event)
+                .expect("DeathLinkEvent always serializes"),
+        )
+    }
+}
+
+impl Link for DeathLink {
+    type Payload = DeathLinkEvent;
+
+    fn tag(&self) -> Tag {
+        Tag::DeathLink
+    }
+
+    /// Only the deaths this participant actually sent are echoes, so other
+    /// clients connected to the same slot can still share deaths with it.
+    fn is_echo(&self, event: &DeathLinkEvent) -> bool {
+        event.source == self.source && self.sent.contains(&event.time)
+    }
+}
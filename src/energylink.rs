@@ -0,0 +1,48 @@
+//! Support for EnergyLink, a pool of energy shared by every slot on a team.
+
+use crate::protocol::TeamId;
+use crate::storage::{DataStorage, KeyChange, SetBuilder};
+
+/// The shared energy pool of a team, stored in the `EnergyLink{team}` data
+/// storage key.
+///
+/// Amounts are handled as floating point numbers because games are free to
+/// deposit fractional energy.
+#[derive(Debug, Clone)]
+pub struct EnergyLink {
+    storage: DataStorage<f64>,
+}
+
+impl EnergyLink {
+    pub fn new(team: TeamId) -> EnergyLink {
+        EnergyLink {
+            storage: DataStorage::new(format!("EnergyLink{team}")),
+        }
+    }
+
+    pub fn storage(&self) -> &DataStorage<f64> {
+        &self.storage
+    }
+
+    /// A write that adds [amount] to the pool.
+    pub fn deposit(&self, amount: f64) -> SetBuilder<f64> {
+        self.storage.set().with_default(0.0).add(amount)
+    }
+
+    /// A write that atomically removes up to [amount] from the pool without
+    /// letting it drop below zero.
+    ///
+    /// Send it with a reply and pass the reply to [EnergyLink::withdrawn] to
+    /// find out how much was actually taken.
+    pub fn withdraw(&self, amount: f64) -> SetBuilder<f64> {
+        self.storage.set().with_default(0.0).add(-amount).max(0.0)
+    }
+
+    /// Returns the energy actually taken by a withdrawal, given the server's
+    /// reply to it.
+    pub fn withdrawn(reply: &KeyChange<f64>) -> f64 {
+        reply
+            .original_value
+            .map_or(0.0, |original| (original - reply.value).max(0.0))
+    }
+}
@@ -0,0 +1,233 @@
+//! Support for the community Gifting API, which lets players send items to
+//! each other through gift boxes in data storage.
+//!
+//! Each team has a motherbox (`GiftBoxes;{team}`) describing which slots have
+//! an open gift box and what they want, and each slot has a gift box
+//! (`GiftBox;{team};{slot}`) holding the gifts waiting for it.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::links;
+use crate::protocol::{SlotId, TeamId};
+use crate::storage::{DataStorage, SetBuilder};
+
+/// The version of the gift data format this module reads and writes.
+pub const GIFT_DATA_VERSION: u32 = 3;
+
+/// A property of a gift, such as "Heal" or "Speed", which lets games that
+/// don't know an item by name decide what to do with it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GiftTrait {
+    #[serde(rename = "trait")]
+    pub name: String,
+
+    /// How strong the trait is, where 1.0 is average.
+    #[serde(default = "one")]
+    pub quality: f64,
+
+    /// How long the trait lasts, where 1.0 is average.
+    #[serde(default = "one")]
+    pub duration: f64,
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+impl GiftTrait {
+    /// A trait of average quality and duration.
+    pub fn new(name: impl Into<String>) -> GiftTrait {
+        GiftTrait {
+            name: name.into(),
+            quality: 1.0,
+            duration: 1.0,
+        }
+    }
+}
+
+/// A gift waiting in a slot's gift box.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Gift {
+    pub id: String,
+    pub item_name: String,
+    pub amount: i64,
+    pub item_value: i64,
+    #[serde(default)]
+    pub traits: Vec<GiftTrait>,
+    pub sender_slot: SlotId,
+    pub receiver_slot: SlotId,
+    pub sender_team: TeamId,
+    pub receiver_team: TeamId,
+    #[serde(default)]
+    pub is_refund: bool,
+}
+
+impl Gift {
+    /// Turns a gift that can't be used into a refund addressed back to its
+    /// sender.
+    pub fn refund(self) -> Gift {
+        Gift {
+            sender_slot: self.receiver_slot,
+            receiver_slot: self.sender_slot,
+            sender_team: self.receiver_team,
+            receiver_team: self.sender_team,
+            is_refund: true,
+            ..self
+        }
+    }
+}
+
+/// A slot's entry in its team's motherbox, describing its gift box.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GiftBoxInfo {
+    pub is_open: bool,
+    pub accepts_any_gift: bool,
+    #[serde(default)]
+    pub desired_traits: Vec<String>,
+    pub minimum_gift_data_version: u32,
+    pub maximum_gift_data_version: u32,
+}
+
+impl GiftBoxInfo {
+    /// Returns whether a gift with [traits] may be sent to this box.
+    pub fn accepts(&self, traits: &[GiftTrait]) -> bool {
+        self.is_open
+            && (self.minimum_gift_data_version..=self.maximum_gift_data_version)
+                .contains(&GIFT_DATA_VERSION)
+            && (self.accepts_any_gift
+                || traits
+                    .iter()
+                    .any(|gift_trait| self.desired_traits.contains(&gift_trait.name)))
+    }
+}
+
+/// A team's motherbox, mapping slot numbers (as strings) to their gift boxes.
+pub type Motherbox = HashMap<String, GiftBoxInfo>;
+
+/// A slot's gift box, mapping gift IDs to gifts.
+pub type GiftBox = HashMap<String, Gift>;
+
+/// The gifting participant for a single slot.
+#[derive(Debug, Clone)]
+pub struct Gifting {
+    team: TeamId,
+    slot: SlotId,
+}
+
+impl Gifting {
+    pub fn new(team: TeamId, slot: SlotId) -> Gifting {
+        Gifting { team, slot }
+    }
+
+    /// The motherbox of [team].
+    pub fn motherbox(team: TeamId) -> DataStorage<Motherbox> {
+        DataStorage::new(format!("GiftBoxes;{team}"))
+    }
+
+    /// The gift box of [slot] on [team].
+    pub fn gift_box(team: TeamId, slot: SlotId) -> DataStorage<GiftBox> {
+        DataStorage::new(format!("GiftBox;{team};{slot}"))
+    }
+
+    /// This participant's own gift box.
+    pub fn own_gift_box(&self) -> DataStorage<GiftBox> {
+        Gifting::gift_box(self.team, self.slot)
+    }
+
+    /// The writes that open this participant's gift box: one advertising it
+    /// in the motherbox, and one creating the box if it doesn't exist yet.
+    pub fn open(
+        &self,
+        accepts_any_gift: bool,
+        desired_traits: Vec<String>,
+    ) -> (SetBuilder<Motherbox>, SetBuilder<GiftBox>) {
+        let info = GiftBoxInfo {
+            is_open: true,
+            accepts_any_gift,
+            desired_traits,
+            minimum_gift_data_version: GIFT_DATA_VERSION,
+            maximum_gift_data_version: GIFT_DATA_VERSION,
+        };
+        (
+            self.advertise(info),
+            self.own_gift_box()
+                .set()
+                .with_default(GiftBox::new())
+                // Merging in nothing leaves an existing box as it is.
+                .update(GiftBox::new()),
+        )
+    }
+
+    /// The write that closes this participant's gift box to new gifts.
+    pub fn close(&self) -> SetBuilder<Motherbox> {
+        self.advertise(GiftBoxInfo {
+            is_open: false,
+            accepts_any_gift: false,
+            desired_traits: Vec::new(),
+            minimum_gift_data_version: GIFT_DATA_VERSION,
+            maximum_gift_data_version: GIFT_DATA_VERSION,
+        })
+    }
+
+    /// Creates a gift from this participant to [receiver_slot] on
+    /// [receiver_team], with a fresh ID.
+    pub fn gift(
+        &self,
+        receiver_team: TeamId,
+        receiver_slot: SlotId,
+        item_name: impl Into<String>,
+        amount: i64,
+        item_value: i64,
+        traits: Vec<GiftTrait>,
+    ) -> Gift {
+        Gift {
+            id: self.next_gift_id(),
+            item_name: item_name.into(),
+            amount,
+            item_value,
+            traits,
+            sender_slot: self.slot,
+            receiver_slot,
+            sender_team: self.team,
+            receiver_team,
+            is_refund: false,
+        }
+    }
+
+    /// The write that adds [gift] to its receiver's gift box.
+    pub fn send(gift: &Gift) -> SetBuilder<GiftBox> {
+        Gifting::gift_box(gift.receiver_team, gift.receiver_slot)
+            .set()
+            .with_default(GiftBox::new())
+            .update(HashMap::from([(gift.id.as_str(), gift)]))
+    }
+
+    /// The write that removes the gifts with [ids] from this participant's
+    /// gift box once they've been received.
+    pub fn remove<'a>(&self, ids: impl IntoIterator<Item = &'a str>) -> SetBuilder<GiftBox> {
+        ids.into_iter().fold(
+            self.own_gift_box().set().with_default(GiftBox::new()),
+            |set, id| set.pop(id),
+        )
+    }
+
+    fn advertise(&self, info: GiftBoxInfo) -> SetBuilder<Motherbox> {
+        Gifting::motherbox(self.team)
+            .set()
+            .with_default(Motherbox::new())
+            .update(HashMap::from([(self.slot.to_string(), info)]))
+    }
+
+    fn next_gift_id(&self) -> String {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let nanos = (links::unix_time() * 1e9) as u128;
+        format!(
+            "{:x}-{:x}-{:x}-{:x}",
+            self.team.0, self.slot.0, nanos, count
+        )
+    }
+}
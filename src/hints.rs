@@ -0,0 +1,150 @@
+//! Tracking hints and the hint points needed to buy them.
+
+use serde_json::Value;
+
+use crate::protocol::{Hint, HintStatus, LocationId, PlayerId, ServerMessage, SlotId, TeamId};
+use crate::storage::{self, DataStorage};
+
+/// The hints relevant to the connected slot, kept current by the client from
+/// SetNotify updates to the slot's `_read_hints_{team}_{slot}` key.
+#[derive(Debug, Clone)]
+pub struct HintBook {
+    key: String,
+    hints: Vec<Hint>,
+}
+
+impl HintBook {
+    pub(crate) fn new(team: TeamId, slot: SlotId) -> HintBook {
+        HintBook {
+            key: DataStorage::hints(team, slot).key().to_string(),
+            hints: Vec::new(),
+        }
+    }
+
+    /// The data storage key this book mirrors.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Every known hint, in the order the server stores them.
+    pub fn hints(&self) -> &[Hint] {
+        &self.hints
+    }
+
+    /// Returns the hint for the item at [location] in [finding_player]'s
+    /// world, if one exists.
+    pub fn for_location(&self, finding_player: PlayerId, location: LocationId) -> Option<&Hint> {
+        self.hints
+            .iter()
+            .find(|hint| hint.finding_player == finding_player && hint.location == location)
+    }
+
+    /// Hints for items that [player] will receive.
+    pub fn received_by(&self, player: PlayerId) -> impl Iterator<Item = &Hint> {
+        self.hints
+            .iter()
+            .filter(move |hint| hint.receiving_player == player)
+    }
+
+    /// Hints for items located in [player]'s world.
+    pub fn found_by(&self, player: PlayerId) -> impl Iterator<Item = &Hint> {
+        self.hints
+            .iter()
+            .filter(move |hint| hint.finding_player == player)
+    }
+
+    /// Hints whose items haven't been found yet.
+    pub fn unfound(&self) -> impl Iterator<Item = &Hint> {
+        self.hints.iter().filter(|hint| !hint.found)
+    }
+
+    /// Hints that have been marked with the given [status].
+    pub fn with_status(&self, status: HintStatus) -> impl Iterator<Item = &Hint> {
+        self.hints.iter().filter(move |hint| hint.status == status)
+    }
+
+    /// Updates the book from a message the client just received.
+    pub(crate) fn handle_message<S>(&mut self, message: &ServerMessage<S>) {
+        match message {
+            ServerMessage::Retrieved(retrieved) => {
+                if let Some(value) = retrieved.keys.get(&self.key) {
+                    self.replace(value);
+                }
+            }
+            ServerMessage::SetReply(reply) if reply.key == self.key => self.replace(&reply.value),
+            _ => {}
+        }
+    }
+
+    /// Replaces the hints with those in [value], the raw value of the key.
+    pub(crate) fn replace(&mut self, value: &Value) {
+        // A missing key means there are no hints yet. Malformed values leave
+        // the previous hints in place, since the raw value remains available
+        // from the storage mirror.
+        if value.is_null() {
+            self.hints.clear();
+        } else if let Ok(hints) = storage::decode_value(value.clone()) {
+            self.hints = hints;
+        }
+    }
+}
+
+/// The connected slot's hint points and what they can buy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HintPoints {
+    /// The points the slot currently has.
+    pub points: i64,
+
+    /// The points a single hint costs. Zero if hints are free.
+    pub cost: i64,
+
+    /// The points the slot earns for each location it checks.
+    pub points_per_check: i64,
+}
+
+impl HintPoints {
+    /// Computes the hint cost the same way the server does: [hint_cost] is a
+    /// percentage of the slot's total number of locations, and any non-zero
+    /// percentage costs at least one point.
+    pub fn new(
+        points: i64,
+        hint_cost: i64,
+        total_locations: usize,
+        points_per_check: i64,
+    ) -> HintPoints {
+        let cost = if hint_cost > 0 {
+            (hint_cost * total_locations as i64 / 100).max(1)
+        } else {
+            0
+        };
+        HintPoints {
+            points,
+            cost,
+            points_per_check,
+        }
+    }
+
+    /// Returns how many hints the slot can currently afford, or `None` if
+    /// hints are free.
+    pub fn affordable(&self) -> Option<i64> {
+        (self.cost > 0).then(|| self.points.max(0) / self.cost)
+    }
+
+    /// Returns whether the slot can afford at least one hint.
+    pub fn can_afford(&self) -> bool {
+        self.affordable().is_none_or(|hints| hints > 0)
+    }
+
+    /// Returns how many more locations must be checked before the next hint
+    /// is affordable, or `None` if checking locations doesn't earn points.
+    pub fn checks_until_next_hint(&self) -> Option<i64> {
+        if self.can_afford() {
+            return Some(0);
+        }
+        if self.points_per_check <= 0 {
+            return None;
+        }
+        let missing = self.cost - self.points;
+        Some((missing + self.points_per_check - 1) / self.points_per_check)
+    }
+}
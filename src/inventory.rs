@@ -0,0 +1,133 @@
+//! A model of the items the connected slot has received.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::{GameData, ItemId, NetworkItem, NetworkItemFlags, PlayerId, ReceivedItems};
+
+/// Every item the server has sent to the connected slot, in the order of the
+/// server's item list, with per-item counts.
+///
+/// The client keeps its inventory current from ReceivedItems packets. It can
+/// be serialized so a game can save it, but after reconnecting the server's
+/// full resend replaces it anyway.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Inventory {
+    history: Vec<NetworkItem>,
+    counts: HashMap<ItemId, usize>,
+}
+
+impl Inventory {
+    /// Every received item, in the order the server sent them.
+    pub fn history(&self) -> &[NetworkItem] {
+        &self.history
+    }
+
+    /// The total number of items received.
+    pub fn len(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.history.is_empty()
+    }
+
+    /// The number of copies of the item with ID [item] received.
+    pub fn count(&self, item: ItemId) -> usize {
+        self.counts.get(&item).copied().unwrap_or(0)
+    }
+
+    /// Returns whether at least one copy of [item] has been received.
+    pub fn has(&self, item: ItemId) -> bool {
+        self.count(item) > 0
+    }
+
+    /// The number of copies received of each item ID.
+    pub fn counts(&self) -> &HashMap<ItemId, usize> {
+        &self.counts
+    }
+
+    /// Received items that have all of [flags].
+    pub fn with_flags(&self, flags: NetworkItemFlags) -> impl Iterator<Item = &NetworkItem> {
+        self.history
+            .iter()
+            .filter(move |item| item.flags.contains(flags))
+    }
+
+    /// Received items that can unlock logical advancement.
+    pub fn progression(&self) -> impl Iterator<Item = &NetworkItem> {
+        self.with_flags(NetworkItemFlags::PROGRESSION)
+    }
+
+    /// Received items that are especially useful.
+    pub fn useful(&self) -> impl Iterator<Item = &NetworkItem> {
+        self.with_flags(NetworkItemFlags::USEFUL)
+    }
+
+    /// Received traps.
+    pub fn traps(&self) -> impl Iterator<Item = &NetworkItem> {
+        self.with_flags(NetworkItemFlags::TRAP)
+    }
+
+    /// Received items that were found in [player]'s world.
+    pub fn from_sender(&self, player: PlayerId) -> impl Iterator<Item = &NetworkItem> {
+        self.history
+            .iter()
+            .filter(move |item| item.player == player)
+    }
+
+    /// The number of items received from each sending player.
+    pub fn sender_counts(&self) -> HashMap<PlayerId, usize> {
+        let mut counts = HashMap::new();
+        for item in &self.history {
+            *counts.entry(item.player).or_default() += 1;
+        }
+        counts
+    }
+
+    /// The number of copies of the item named [name] received, looked up in
+    /// the connected game's data package.
+    pub fn count_by_name(&self, name: &str, game: &GameData) -> usize {
+        game.item_name_to_id
+            .get(name)
+            .map_or(0, |id| self.count(*id))
+    }
+
+    /// The number of copies received of each item, by name. Items missing
+    /// from [game] are left out.
+    pub fn counts_by_name<'a>(&self, game: &'a GameData) -> HashMap<&'a str, usize> {
+        game.item_name_to_id
+            .iter()
+            .filter_map(|(name, id)| {
+                let count = self.count(*id);
+                (count > 0).then_some((name.as_str(), count))
+            })
+            .collect()
+    }
+
+    /// Adds the new items of a ReceivedItems packet. A packet starting at
+    /// index 0 is a full resend and replaces the inventory.
+    ///
+    /// Returns false if items are missing before the packet, in which case
+    /// it's ignored and a Sync is needed.
+    pub fn receive(&mut self, received: &ReceivedItems) -> bool {
+        if received.index == 0 {
+            self.history.clear();
+            self.counts.clear();
+        }
+        let known = self.history.len() as i64;
+        if received.index > known {
+            return false;
+        }
+        for item in received
+            .items
+            .iter()
+            .skip((known - received.index) as usize)
+        {
+            *self.counts.entry(item.item).or_default() += 1;
+            self.history.push(item.clone());
+        }
+        true
+    }
+}
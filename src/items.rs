@@ -0,0 +1,266 @@
+//! Delivering received items to the game exactly once.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::lookup::{CHEAT_CONSOLE_LOCATION, SERVER_LOCATION};
+use crate::protocol::{ItemId, LocationId, NetworkItem, NetworkItemFlags, PlayerId, ReceivedItems};
+use crate::text::TextResolver;
+
+/// What an [ItemDelivery] did with a ReceivedItems packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiveOutcome {
+    /// The packet continued the known item list, and this many of its items
+    /// were new.
+    Queued(usize),
+
+    /// Items are missing between the known list and the packet, so it was
+    /// dropped. A Sync is needed to get a full resend.
+    Gap,
+
+    /// The server resent its full item list and it's shorter than the items
+    /// already applied, so the applied index doesn't match this server's
+    /// state (for example, a save file from another seed). Items are only
+    /// delivered again once the list grows past the applied index.
+    Reset,
+}
+
+/// Tracks which received items the game has applied, so that every item is
+/// handed to it exactly once across reconnects and resyncs.
+///
+/// Only the applied index needs to be persisted, and it should be saved
+/// together with the game state the items were applied to.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ItemDelivery {
+    applied: i64,
+
+    /// The index just past the last item received from the server.
+    #[serde(skip)]
+    received: i64,
+
+    /// Received items the game hasn't taken yet, with their indices.
+    #[serde(skip)]
+    pending: VecDeque<(i64, NetworkItem)>,
+}
+
+impl ItemDelivery {
+    /// Resumes delivery for a game that has already applied the first
+    /// [applied_index] items.
+    pub fn new(applied_index: i64) -> ItemDelivery {
+        ItemDelivery {
+            applied: applied_index,
+            received: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// The number of items the game has applied. Persist this with the game's
+    /// save file and pass it to [ItemDelivery::new] when resuming.
+    pub fn applied_index(&self) -> i64 {
+        self.applied
+    }
+
+    /// Returns whether items are waiting to be taken by the game.
+    pub fn has_pending(&self) -> bool {
+        self.pending.iter().any(|(index, _)| *index >= self.applied)
+    }
+
+    /// Takes the items the game hasn't applied yet, in order, and marks them
+    /// as applied.
+    pub fn take(&mut self) -> Vec<NetworkItem> {
+        let mut items = Vec::new();
+        for (index, item) in self.pending.drain(..) {
+            if index >= self.applied {
+                self.applied = index + 1;
+                items.push(item);
+            }
+        }
+        items
+    }
+
+    /// Queues the new items of a ReceivedItems packet.
+    pub fn receive(&mut self, received: &ReceivedItems) -> ReceiveOutcome {
+        // Before anything has arrived, the applied items are the only known
+        // part of the list.
+        let known = self.received.max(self.applied);
+        let end = received.index + received.items.len() as i64;
+        if received.index > known {
+            return ReceiveOutcome::Gap;
+        }
+        if received.index == 0 && end < known {
+            self.pending.clear();
+            self.received = end;
+            return ReceiveOutcome::Reset;
+        }
+
+        let mut queued = 0;
+        for (index, item) in (received.index..).zip(&received.items) {
+            if index >= known {
+                self.pending.push_back((index, item.clone()));
+                queued += 1;
+            }
+        }
+        self.received = known.max(end);
+        ReceiveOutcome::Queued(queued)
+    }
+}
+
+/// Where a received item came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ItemOrigin {
+    /// The item was found at a location in a player's world, possibly the
+    /// connected player's own.
+    World,
+
+    /// The item is part of the slot's starting inventory, sent by the server
+    /// from the [SERVER_LOCATION].
+    StartingInventory,
+
+    /// The item was given by the server's cheat console, from the
+    /// [CHEAT_CONSOLE_LOCATION].
+    Cheat,
+}
+
+impl ItemOrigin {
+    /// The origin of an item that was sent from [location].
+    pub fn of(location: LocationId) -> ItemOrigin {
+        match location {
+            SERVER_LOCATION => ItemOrigin::StartingInventory,
+            CHEAT_CONSOLE_LOCATION => ItemOrigin::Cheat,
+            _ => ItemOrigin::World,
+        }
+    }
+}
+
+/// A received item along with everything needed to tell the player about it,
+/// such as "You got Hookshot from Bob (A Link to the Past)".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedItem {
+    pub item: ItemId,
+    pub item_name: String,
+    pub flags: NetworkItemFlags,
+
+    /// The player whose world the item was found in, or the server's slot
+    /// for items that weren't found in a world.
+    pub sender: PlayerId,
+    pub sender_name: String,
+
+    /// The game the sender is playing, if it's known.
+    pub sender_game: Option<String>,
+    pub location: LocationId,
+    pub location_name: String,
+    pub origin: ItemOrigin,
+}
+
+impl ReceivedItem {
+    /// Describes [item], which was received by the connected slot, with the
+    /// names [resolver] finds for it.
+    pub fn new(item: &NetworkItem, resolver: &TextResolver) -> ReceivedItem {
+        ReceivedItem {
+            item: item.item,
+            item_name: resolver.item_name(resolver.own_slot(), item.item),
+            flags: item.flags,
+            sender: item.player,
+            sender_name: resolver.player_name(item.player),
+            sender_game: resolver.player_game(item.player).map(str::to_string),
+            location: item.location,
+            location_name: resolver.location_name(item.player, item.location),
+            origin: ItemOrigin::of(item.location),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(index: i64, items: &[i64]) -> ReceivedItems {
+        ReceivedItems {
+            index,
+            items: items
+                .iter()
+                .map(|item| NetworkItem {
+                    item: ItemId(*item),
+                    location: LocationId(*item),
+                    player: PlayerId::from(1),
+                    flags: NetworkItemFlags::empty(),
+                })
+                .collect(),
+        }
+    }
+
+    fn taken(delivery: &mut ItemDelivery) -> Vec<i64> {
+        delivery.take().iter().map(|item| item.item.0).collect()
+    }
+
+    #[test]
+    fn delivers_a_growing_list_once() {
+        let mut delivery = ItemDelivery::new(0);
+        assert_eq!(
+            delivery.receive(&packet(0, &[1, 2])),
+            ReceiveOutcome::Queued(2)
+        );
+        assert_eq!(taken(&mut delivery), [1, 2]);
+        assert_eq!(
+            delivery.receive(&packet(2, &[3])),
+            ReceiveOutcome::Queued(1)
+        );
+        assert_eq!(taken(&mut delivery), [3]);
+        assert_eq!(delivery.applied_index(), 3);
+        assert!(taken(&mut delivery).is_empty());
+    }
+
+    #[test]
+    fn drops_a_packet_after_a_gap() {
+        let mut delivery = ItemDelivery::new(0);
+        delivery.receive(&packet(0, &[1]));
+        assert_eq!(delivery.receive(&packet(2, &[3])), ReceiveOutcome::Gap);
+        assert_eq!(taken(&mut delivery), [1]);
+        assert_eq!(
+            delivery.receive(&packet(1, &[2, 3])),
+            ReceiveOutcome::Queued(2)
+        );
+        assert_eq!(taken(&mut delivery), [2, 3]);
+    }
+
+    #[test]
+    fn skips_applied_items_in_a_full_resend() {
+        let mut delivery = ItemDelivery::new(2);
+        assert!(!delivery.has_pending());
+        assert_eq!(
+            delivery.receive(&packet(0, &[1, 2, 3])),
+            ReceiveOutcome::Queued(1)
+        );
+        assert_eq!(taken(&mut delivery), [3]);
+        assert_eq!(
+            delivery.receive(&packet(0, &[1, 2, 3, 4])),
+            ReceiveOutcome::Queued(1)
+        );
+        assert_eq!(taken(&mut delivery), [4]);
+    }
+
+    #[test]
+    fn queues_only_the_new_part_of_an_overlapping_packet() {
+        let mut delivery = ItemDelivery::new(0);
+        delivery.receive(&packet(0, &[1, 2, 3]));
+        assert_eq!(
+            delivery.receive(&packet(1, &[2, 3, 4, 5])),
+            ReceiveOutcome::Queued(2)
+        );
+        assert_eq!(taken(&mut delivery), [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn reports_a_resend_shorter_than_the_applied_index() {
+        let mut delivery = ItemDelivery::new(5);
+        assert_eq!(delivery.receive(&packet(0, &[1, 2])), ReceiveOutcome::Reset);
+        assert!(!delivery.has_pending());
+        assert_eq!(delivery.applied_index(), 5);
+        assert_eq!(
+            delivery.receive(&packet(2, &[3, 4, 5, 6])),
+            ReceiveOutcome::Queued(1)
+        );
+        assert_eq!(taken(&mut delivery), [6]);
+    }
+}
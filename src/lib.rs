@@ -1,5 +1,22 @@
 //! A Rust library that for the [Archipelago game randomizer](archipelago.gg), that implements the [Archipelago network protocol](https://github.com/ArchipelagoMW/Archipelago/blob/main/docs/network%20protocol.md)
 //! Check out ArchipelagoClient for the meat of the logic
 
+pub mod cache;
+pub mod checksum;
 pub mod client;
+pub mod codegen;
+pub mod deathlink;
+pub mod energylink;
+pub mod gifting;
+pub mod hints;
+pub mod inventory;
+pub mod items;
+pub mod links;
+pub mod lookup;
+pub mod outbox;
+pub mod profile;
 pub mod protocol;
+pub mod search;
+pub mod session;
+pub mod storage;
+pub mod text;
@@ -0,0 +1,300 @@
+//! A framework for features that share events between clients through
+//! Bounce messages, such as DeathLink, TrapLink and RingLink.
+
+use std::any::Any;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::{Bounce, Bounced, SlotId, Tag};
+
+/// A feature that shares events between clients by bouncing a payload to
+/// every client with the link's tag.
+pub trait Link: Send + 'static {
+    /// The data carried by the link's bounces.
+    type Payload: Serialize + DeserializeOwned;
+
+    /// The tag the link's bounces are sent to. Clients must also connect with
+    /// this tag to receive them.
+    fn tag(&self) -> Tag;
+
+    /// Returns whether [payload] is this client's own event coming back to
+    /// it, which should be ignored.
+    fn is_echo(&self, payload: &Self::Payload) -> bool;
+
+    /// Builds the Bounce that shares [payload] with other participants.
+    fn bounce(&self, payload: &Self::Payload) -> Result<Bounce, serde_json::Error> {
+        Ok(Bounce {
+            games: None,
+            slots: None,
+            tags: Some(vec![self.tag()]),
+            data: serde_json::to_value(payload)?,
+        })
+    }
+
+    /// Decodes another participant's event from a Bounced message.
+    ///
+    /// Returns `None` if the message doesn't carry this link's tag, is
+    /// malformed, or is an echo.
+    fn receive(&self, bounced: &Bounced) -> Option<Self::Payload> {
+        if !bounced.tags.contains(&self.tag()) {
+            return None;
+        }
+        let payload = bounced.decode().ok()?.data;
+        (!self.is_echo(&payload)).then_some(payload)
+    }
+}
+
+/// Selects which Bounced messages to receive by their tag and target game.
+///
+/// An empty filter matches every Bounced message.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BounceFilter {
+    pub tag: Option<Tag>,
+    pub game: Option<String>,
+}
+
+impl BounceFilter {
+    /// A filter for messages carrying [tag].
+    pub fn tag(tag: Tag) -> BounceFilter {
+        BounceFilter {
+            tag: Some(tag),
+            game: None,
+        }
+    }
+
+    /// A filter for messages targeted at [game].
+    pub fn game(game: impl Into<String>) -> BounceFilter {
+        BounceFilter {
+            tag: None,
+            game: Some(game.into()),
+        }
+    }
+
+    /// Additionally requires messages to be targeted at [game].
+    pub fn with_game(mut self, game: impl Into<String>) -> BounceFilter {
+        self.game = Some(game.into());
+        self
+    }
+
+    pub fn matches<T>(&self, bounced: &Bounced<T>) -> bool {
+        let tag_matches = self
+            .tag
+            .as_ref()
+            .is_none_or(|tag| bounced.tags.contains(tag));
+        let game_matches = self.game.as_ref().is_none_or(|game| {
+            bounced
+                .games
+                .as_ref()
+                .is_some_and(|games| games.contains(game))
+        });
+        tag_matches && game_matches
+    }
+}
+
+/// Returns the current Unix timestamp in seconds, as links use to mark when
+/// an event happened.
+pub(crate) fn unix_time() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |duration| duration.as_secs_f64())
+}
+
+/// A trap, as carried in the data of a TrapLink Bounce.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrapLinkEvent {
+    /// The Unix timestamp of the trap, in seconds.
+    pub time: f64,
+
+    /// The name of the player who received the trap.
+    pub source: String,
+
+    /// The name of the trap item, which receiving games map onto their own
+    /// traps.
+    pub trap_name: String,
+}
+
+/// TrapLink, which shares the traps a player receives with every
+/// participating player.
+#[derive(Debug, Clone)]
+pub struct TrapLink {
+    source: String,
+}
+
+impl TrapLink {
+    /// Creates a TrapLink participant that reports traps as [source], usually
+    /// the connected slot's name.
+    pub fn new(source: impl Into<String>) -> TrapLink {
+        TrapLink {
+            source: source.into(),
+        }
+    }
+
+    /// Returns an event for receiving the trap named [trap_name] right now.
+    pub fn trap(&self, trap_name: impl Into<String>) -> TrapLinkEvent {
+        TrapLinkEvent {
+            time: unix_time(),
+            source: self.source.clone(),
+            trap_name: trap_name.into(),
+        }
+    }
+}
+
+impl Link for TrapLink {
+    type Payload = TrapLinkEvent;
+
+    fn tag(&self) -> Tag {
+        Tag::TrapLink
+    }
+
+    fn is_echo(&self, payload: &TrapLinkEvent) -> bool {
+        payload.source == self.source
+    }
+}
+
+/// A change in rings, as carried in the data of a RingLink Bounce.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RingLinkEvent {
+    /// The Unix timestamp of the change, in seconds.
+    pub time: f64,
+
+    /// The slot number of the player whose rings changed.
+    pub source: SlotId,
+
+    /// The number of rings gained, or lost if negative.
+    pub amount: i64,
+}
+
+/// RingLink, which shares every change in a player's ring count with every
+/// participating player.
+#[derive(Debug, Clone)]
+pub struct RingLink {
+    slot: SlotId,
+}
+
+impl RingLink {
+    /// Creates a RingLink participant for the connected [slot].
+    pub fn new(slot: SlotId) -> RingLink {
+        RingLink { slot }
+    }
+
+    /// Returns an event for gaining (or losing, if negative) [amount] rings
+    /// right now.
+    pub fn rings(&self, amount: i64) -> RingLinkEvent {
+        RingLinkEvent {
+            time: unix_time(),
+            source: self.slot,
+            amount,
+        }
+    }
+}
+
+impl Link for RingLink {
+    type Payload = RingLinkEvent;
+
+    fn tag(&self) -> Tag {
+        Tag::RingLink
+    }
+
+    fn is_echo(&self, payload: &RingLinkEvent) -> bool {
+        payload.source == self.slot
+    }
+}
+
+/// Routes incoming Bounced messages to the handlers of registered links.
+///
+/// The router owns its links so that state used for echo filtering (such as
+/// the deaths a [DeathLink](crate::deathlink::DeathLink) has sent) stays in
+/// one place. Use [LinkRouter::link_mut] to reach a link when sending.
+#[derive(Default)]
+pub struct LinkRouter {
+    handlers: Vec<Box<dyn Handler>>,
+}
+
+impl LinkRouter {
+    pub fn new() -> LinkRouter {
+        LinkRouter::default()
+    }
+
+    /// Registers [link], calling [callback] with each event other
+    /// participants send through it.
+    pub fn register<L, F>(&mut self, link: L, callback: F) -> &mut LinkRouter
+    where
+        L: Link,
+        F: FnMut(L::Payload) + Send + 'static,
+    {
+        self.handlers.push(Box::new(LinkHandler { link, callback }));
+        self
+    }
+
+    /// Returns the registered link of type [L].
+    pub fn link<L: Link>(&self) -> Option<&L> {
+        self.handlers
+            .iter()
+            .find_map(|handler| handler.link().downcast_ref())
+    }
+
+    /// Returns the registered link of type [L] mutably.
+    pub fn link_mut<L: Link>(&mut self) -> Option<&mut L> {
+        self.handlers
+            .iter_mut()
+            .find_map(|handler| handler.link_mut().downcast_mut())
+    }
+
+    /// The tags of every registered link, to connect (or `ConnectUpdate`)
+    /// with.
+    pub fn tags(&self) -> Vec<Tag> {
+        self.handlers.iter().map(|handler| handler.tag()).collect()
+    }
+
+    /// Passes [bounced] to every link whose tag it carries, returning whether
+    /// any of them accepted it.
+    pub fn route(&mut self, bounced: &Bounced) -> bool {
+        let mut handled = false;
+        for handler in &mut self.handlers {
+            handled |= handler.handle(bounced);
+        }
+        handled
+    }
+}
+
+trait Handler: Send {
+    fn tag(&self) -> Tag;
+    fn handle(&mut self, bounced: &Bounced) -> bool;
+    fn link(&self) -> &dyn Any;
+    fn link_mut(&mut self) -> &mut dyn Any;
+}
+
+struct LinkHandler<L, F> {
+    link: L,
+    callback: F,
+}
+
+impl<L, F> Handler for LinkHandler<L, F>
+where
+    L: Link,
+    F: FnMut(L::Payload) + Send,
+{
+    fn tag(&self) -> Tag {
+        self.link.tag()
+    }
+
+    fn handle(&mut self, bounced: &Bounced) -> bool {
+        match self.link.receive(bounced) {
+            Some(payload) => {
+                (self.callback)(payload);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn link(&self) -> &dyn Any {
+        &self.link
+    }
+
+    fn link_mut(&mut self) -> &mut dyn Any {
+        &mut self.link
+    }
+}
@@ -0,0 +1,164 @@
+//! Resolving item and location IDs to names and back.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::protocol::{
+    DataPackageObject, GameData, ItemId, LocationId, NetworkSlot, PlayerId, SlotId,
+};
+
+/// The name of the built-in game that owns the IDs shared by every world,
+/// such as the locations of cheated and starting inventory items.
+pub const ARCHIPELAGO_GAME: &str = "Archipelago";
+
+/// The slot number the server sends items as, such as starting inventory
+/// and cheated items. It's named after the [ARCHIPELAGO_GAME].
+pub const SERVER_SLOT: SlotId = SlotId(0);
+
+/// The item ID of the built-in "Nothing" item.
+pub const NOTHING_ITEM: ItemId = ItemId(-1);
+
+/// The location ID of items sent by the server's cheat console.
+pub const CHEAT_CONSOLE_LOCATION: LocationId = LocationId(-1);
+
+/// The location ID of items from the starting inventory, which the server
+/// sends itself.
+pub const SERVER_LOCATION: LocationId = LocationId(-2);
+
+/// Item and location names of a single game, indexed in both directions.
+#[derive(Debug, Clone, Default)]
+pub struct GameLookup {
+    item_names: HashMap<ItemId, String>,
+    location_names: HashMap<LocationId, String>,
+    item_ids: HashMap<String, ItemId>,
+    location_ids: HashMap<String, LocationId>,
+}
+
+impl GameLookup {
+    pub fn new(data: &GameData) -> GameLookup {
+        GameLookup::from_maps(
+            data.item_name_to_id.clone().into_iter().collect(),
+            data.location_name_to_id.clone().into_iter().collect(),
+        )
+    }
+
+    /// The lookup for the built-in [ARCHIPELAGO_GAME], used when the data
+    /// package doesn't include it.
+    pub fn archipelago() -> GameLookup {
+        GameLookup::from_maps(
+            HashMap::from([("Nothing".to_string(), NOTHING_ITEM)]),
+            HashMap::from([
+                ("Cheat Console".to_string(), CHEAT_CONSOLE_LOCATION),
+                ("Server".to_string(), SERVER_LOCATION),
+            ]),
+        )
+    }
+
+    fn from_maps(
+        item_ids: HashMap<String, ItemId>,
+        location_ids: HashMap<String, LocationId>,
+    ) -> GameLookup {
+        GameLookup {
+            item_names: invert(&item_ids),
+            location_names: invert(&location_ids),
+            item_ids,
+            location_ids,
+        }
+    }
+
+    pub fn item_name(&self, item: ItemId) -> Option<&str> {
+        self.item_names.get(&item).map(String::as_str)
+    }
+
+    pub fn location_name(&self, location: LocationId) -> Option<&str> {
+        self.location_names.get(&location).map(String::as_str)
+    }
+
+    pub fn item_id(&self, name: &str) -> Option<ItemId> {
+        self.item_ids.get(name).copied()
+    }
+
+    pub fn location_id(&self, name: &str) -> Option<LocationId> {
+        self.location_ids.get(name).copied()
+    }
+}
+
+fn invert<T: Copy + Eq + Hash>(ids: &HashMap<String, T>) -> HashMap<T, String> {
+    ids.iter().map(|(name, id)| (*id, name.clone())).collect()
+}
+
+/// Name lookups for every game in a data package, along with which game each
+/// slot plays.
+///
+/// IDs are only unique within a game, so item and location IDs are resolved
+/// in the game of the player they belong to. IDs the player's game doesn't
+/// know fall back to the built-in [ARCHIPELAGO_GAME].
+#[derive(Debug, Clone)]
+pub struct NameLookup {
+    games: HashMap<String, GameLookup>,
+    slot_games: HashMap<SlotId, String>,
+}
+
+impl NameLookup {
+    pub fn new(
+        package: &DataPackageObject,
+        slot_info: &HashMap<SlotId, NetworkSlot>,
+    ) -> NameLookup {
+        let mut games: HashMap<String, GameLookup> = package
+            .games
+            .iter()
+            .map(|(game, data)| (game.clone(), GameLookup::new(data)))
+            .collect();
+        games
+            .entry(ARCHIPELAGO_GAME.to_string())
+            .or_insert_with(GameLookup::archipelago);
+        NameLookup {
+            games,
+            slot_games: slot_info
+                .iter()
+                .map(|(slot, info)| (*slot, info.game.clone()))
+                .collect(),
+        }
+    }
+
+    /// The lookup for [game], if it's in the data package.
+    pub fn game(&self, game: &str) -> Option<&GameLookup> {
+        self.games.get(game)
+    }
+
+    /// The game [player] is playing.
+    pub fn player_game(&self, player: PlayerId) -> Option<&str> {
+        self.slot_games.get(&player).map(String::as_str)
+    }
+
+    /// The name of [item] in [player]'s game.
+    pub fn item_name(&self, player: PlayerId, item: ItemId) -> Option<&str> {
+        self.resolve(player, |game| game.item_name(item))
+    }
+
+    /// The name of [location] in [player]'s game.
+    pub fn location_name(&self, player: PlayerId, location: LocationId) -> Option<&str> {
+        self.resolve(player, |game| game.location_name(location))
+    }
+
+    /// The ID of the item named [name] in [player]'s game.
+    pub fn item_id(&self, player: PlayerId, name: &str) -> Option<ItemId> {
+        self.resolve(player, |game| game.item_id(name))
+    }
+
+    /// The ID of the location named [name] in [player]'s game.
+    pub fn location_id(&self, player: PlayerId, name: &str) -> Option<LocationId> {
+        self.resolve(player, |game| game.location_id(name))
+    }
+
+    fn resolve<'a, T>(
+        &'a self,
+        player: PlayerId,
+        lookup: impl Fn(&'a GameLookup) -> Option<T>,
+    ) -> Option<T> {
+        self.player_game(player)
+            .and_then(|game| self.game(game))
+            .and_then(&lookup)
+            .or_else(|| self.game(ARCHIPELAGO_GAME).and_then(&lookup))
+    }
+}
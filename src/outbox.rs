@@ -0,0 +1,65 @@
+//! A durable queue of location checks that haven't been acknowledged yet.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::client::ArchipelagoError;
+use crate::protocol::LocationId;
+
+/// Location checks waiting for the server to acknowledge them.
+///
+/// A check only counts as acknowledged once the server reports the location
+/// as checked, in Connected or RoomUpdate. Until then it stays queued and is
+/// resent after every reconnect, so saving the outbox to disk keeps checks
+/// made while offline (or just before a disconnect) from being lost.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LocationOutbox {
+    pending: BTreeSet<LocationId>,
+}
+
+impl LocationOutbox {
+    /// Reads an outbox previously written with [LocationOutbox::save].
+    pub fn load(path: impl AsRef<Path>) -> Result<LocationOutbox, ArchipelagoError> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json)
+            .map_err(|error| ArchipelagoError::FailedDeserialize { json, error })
+    }
+
+    /// Writes this outbox to [path] as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ArchipelagoError> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// The checks that haven't been acknowledged, in ascending order.
+    pub fn pending(&self) -> &BTreeSet<LocationId> {
+        &self.pending
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Queues [locations], returning the ones that weren't already queued.
+    pub fn queue(&mut self, locations: impl IntoIterator<Item = LocationId>) -> Vec<LocationId> {
+        locations
+            .into_iter()
+            .filter(|location| self.pending.insert(*location))
+            .collect()
+    }
+
+    /// Keeps only the queued checks for which [keep] returns true.
+    pub fn retain(&mut self, keep: impl FnMut(&LocationId) -> bool) {
+        self.pending.retain(keep);
+    }
+
+    /// Removes [checked] locations, which the server has reported as checked.
+    pub fn acknowledge<'a>(&mut self, checked: impl IntoIterator<Item = &'a LocationId>) {
+        for location in checked {
+            self.pending.remove(location);
+        }
+    }
+}
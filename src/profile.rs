@@ -0,0 +1,66 @@
+//! Preset combinations of tags and item handling for common kinds of clients.
+
+use crate::protocol::{ItemsHandlingFlags, Tag};
+
+/// The tags and item handling a client announces when it connects to a slot.
+///
+/// The presets cover the usual kinds of clients so that the tags and
+/// [ItemsHandlingFlags] always agree with each other. Extra tags (such as
+/// [Tag::DeathLink]) can be added on top with [ConnectProfile::with_tag].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectProfile {
+    pub tags: Vec<Tag>,
+    pub items_handling: ItemsHandlingFlags,
+}
+
+impl ConnectProfile {
+    /// A profile for a client connected to an actual game, which receives
+    /// every item meant for its slot.
+    pub fn game_client() -> ConnectProfile {
+        ConnectProfile {
+            tags: vec![Tag::AP],
+            items_handling: ItemsHandlingFlags::all(),
+        }
+    }
+
+    /// A profile for a chat and command client that isn't attached to a game.
+    ///
+    /// Like the official text client, it still receives items so they can be
+    /// displayed. Text clients usually connect with an empty game name.
+    pub fn text_client() -> ConnectProfile {
+        ConnectProfile {
+            tags: vec![Tag::AP, Tag::TextOnly],
+            items_handling: ItemsHandlingFlags::all(),
+        }
+    }
+
+    /// A profile for a tracker, which observes a slot without receiving items.
+    pub fn tracker() -> ConnectProfile {
+        ConnectProfile {
+            tags: vec![Tag::Tracker],
+            items_handling: ItemsHandlingFlags::empty(),
+        }
+    }
+
+    /// A profile for a client that only watches the multiworld's messages.
+    pub fn spectator() -> ConnectProfile {
+        ConnectProfile {
+            tags: vec![Tag::TextOnly],
+            items_handling: ItemsHandlingFlags::empty(),
+        }
+    }
+
+    /// Adds [tag] to this profile if it isn't already present.
+    pub fn with_tag(mut self, tag: Tag) -> ConnectProfile {
+        if !self.tags.contains(&tag) {
+            self.tags.push(tag);
+        }
+        self
+    }
+
+    /// Replaces the item handling of this profile.
+    pub fn with_items_handling(mut self, items_handling: ItemsHandlingFlags) -> ConnectProfile {
+        self.items_handling = items_handling;
+        self
+    }
+}
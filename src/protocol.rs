@@ -3,12 +3,77 @@ use std::collections::HashMap;
 use std::fmt::Display;
 
 use bitflags::bitflags;
+use indexmap::IndexMap;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use serde_with::serde_as;
 use std::fmt;
 
+/// Declares a newtype for one kind of ID that serializes as the bare number.
+macro_rules! id_type {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(
+            Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+        )]
+        #[serde(transparent)]
+        pub struct $name(pub i64);
+
+        impl From<i64> for $name {
+            fn from(id: i64) -> $name {
+                $name(id)
+            }
+        }
+
+        impl From<$name> for i64 {
+            fn from(id: $name) -> i64 {
+                id.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = std::num::ParseIntError;
+
+            fn from_str(s: &str) -> Result<$name, Self::Err> {
+                s.parse().map($name)
+            }
+        }
+    };
+}
+
+id_type! {
+    /// The ID of an item, which is only unique within the game it belongs to.
+    ItemId
+}
+
+id_type! {
+    /// The ID of a location, which is only unique within the game it belongs
+    /// to.
+    LocationId
+}
+
+id_type! {
+    /// The number of a team in a multiworld.
+    TeamId
+}
+
+id_type! {
+    /// The number of a slot in a multiworld, which is the same on every team.
+    SlotId
+}
+
+/// The protocol refers to the slot a player is on as their player number, so
+/// player IDs are slot IDs.
+pub type PlayerId = SlotId;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "cmd")]
 pub enum ClientMessage {
@@ -87,33 +152,29 @@ pub struct NetworkVersion {
 
 impl Display for NetworkVersion {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            format!("{}.{}.{}", self.major, self.minor, self.build)
-        )
+        write!(f, "{}.{}.{}", self.major, self.minor, self.build)
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkPlayer {
-    pub team: i64,
-    pub slot: i64,
+    pub team: TeamId,
+    pub slot: SlotId,
     pub alias: String,
     pub name: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkItem {
-    pub item: i64,
-    pub location: i64,
-    pub player: i64,
+    pub item: ItemId,
+    pub location: LocationId,
+    pub player: PlayerId,
     pub flags: NetworkItemFlags,
 }
 
 bitflags! {
     #[repr(transparent)]
-    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
     #[serde(from = "u8")]
     #[serde(into = "u8")]
     pub struct NetworkItemFlags: u8 {
@@ -134,9 +195,9 @@ impl From<u8> for NetworkItemFlags {
     }
 }
 
-impl Into<u8> for NetworkItemFlags {
-    fn into(self) -> u8 {
-        self.bits()
+impl From<NetworkItemFlags> for u8 {
+    fn from(value: NetworkItemFlags) -> u8 {
+        value.bits()
     }
 }
 
@@ -153,7 +214,117 @@ pub struct NetworkSlot {
     pub name: String,
     pub game: String,
     pub r#type: SlotType,
-    pub group_members: Vec<i64>,
+    pub group_members: Vec<SlotId>,
+}
+
+/// A tag sent in [Connect] or [ConnectUpdate] describing what a client is and
+/// which optional features it participates in.
+///
+/// Tags that aren't known to this crate are preserved as [Tag::Custom]. Tags
+/// are compared by their protocol string, so a [Tag::Custom] spelling out a
+/// known tag is equal to that tag.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "String")]
+#[serde(into = "String")]
+pub enum Tag {
+    /// The client is a full game client that sends and receives items.
+    AP,
+
+    /// The client participates in the DeathLink mechanic.
+    DeathLink,
+
+    /// The client is a hint game, giving hints to players instead of items.
+    HintGame,
+
+    /// The client is a tracker that only observes the multiworld.
+    Tracker,
+
+    /// The client is a basic text client, such as a chat or command client.
+    TextOnly,
+
+    /// The client doesn't want to receive text messages.
+    NoText,
+
+    /// The client participates in the community TrapLink mechanic.
+    TrapLink,
+
+    /// The client participates in the community RingLink mechanic.
+    RingLink,
+
+    /// Any tag not otherwise covered by this enum.
+    Custom(String),
+}
+
+impl Tag {
+    /// Returns the string the protocol uses for this tag.
+    pub fn as_str(&self) -> &str {
+        use Tag::*;
+        match self {
+            AP => "AP",
+            DeathLink => "DeathLink",
+            HintGame => "HintGame",
+            Tracker => "Tracker",
+            TextOnly => "TextOnly",
+            NoText => "NoText",
+            TrapLink => "TrapLink",
+            RingLink => "RingLink",
+            Custom(tag) => tag,
+        }
+    }
+}
+
+impl From<&str> for Tag {
+    fn from(value: &str) -> Tag {
+        match value {
+            "AP" => Tag::AP,
+            "DeathLink" => Tag::DeathLink,
+            "HintGame" => Tag::HintGame,
+            "Tracker" => Tag::Tracker,
+            "TextOnly" => Tag::TextOnly,
+            "NoText" => Tag::NoText,
+            "TrapLink" => Tag::TrapLink,
+            "RingLink" => Tag::RingLink,
+            _ => Tag::Custom(value.to_string()),
+        }
+    }
+}
+
+impl From<String> for Tag {
+    fn from(value: String) -> Tag {
+        match Tag::from(value.as_str()) {
+            Tag::Custom(_) => Tag::Custom(value),
+            tag => tag,
+        }
+    }
+}
+
+impl From<Tag> for String {
+    fn from(value: Tag) -> String {
+        match value {
+            Tag::Custom(tag) => tag,
+            tag => tag.as_str().to_string(),
+        }
+    }
+}
+
+impl PartialEq for Tag {
+    fn eq(&self, other: &Tag) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Tag {}
+
+impl std::hash::Hash for Tag {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 pub fn network_version() -> NetworkVersion {
@@ -174,53 +345,81 @@ pub struct Connect {
     pub name: String,
     pub uuid: String,
     pub version: NetworkVersion,
-    pub items_handling: u8,
-    pub tags: Vec<String>,
+    pub items_handling: ItemsHandlingFlags,
+    pub tags: Vec<Tag>,
     #[serde(rename = "slot_data")]
     pub slot_data: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectUpdate {
-    pub items_handling: u8,
-    pub tags: Vec<String>,
+    pub items_handling: ItemsHandlingFlags,
+    pub tags: Vec<Tag>,
 }
 
 bitflags! {
     #[repr(transparent)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    #[serde(from = "u8")]
+    #[serde(into = "u8")]
     pub struct ItemsHandlingFlags: u8 {
         /// Items are sent from other worlds.
         const OTHER_WORLDS = 0b001;
 
-        /// Items are sent from your own world. Setting this automatically sets
-        /// [OTHER_WORLDS] as well.
-        const OWN_WORLD = 0b011;
+        /// Items are sent from your own world. Only valid together with
+        /// [OTHER_WORLDS].
+        const OWN_WORLD = 0b010;
+
+        /// Items are sent from your starting inventory. Only valid together
+        /// with [OTHER_WORLDS].
+        const STARTING_INVENTORY = 0b100;
+    }
+}
+
+impl ItemsHandlingFlags {
+    /// Returns whether the server will accept these flags.
+    ///
+    /// [OWN_WORLD] and [STARTING_INVENTORY] describe items that are sent
+    /// remotely in addition to those from other worlds, so they're only
+    /// meaningful when [OTHER_WORLDS] is also set.
+    pub fn is_valid(&self) -> bool {
+        self.contains(ItemsHandlingFlags::OTHER_WORLDS)
+            || !self
+                .intersects(ItemsHandlingFlags::OWN_WORLD | ItemsHandlingFlags::STARTING_INVENTORY)
+    }
+}
+
+impl From<u8> for ItemsHandlingFlags {
+    fn from(value: u8) -> ItemsHandlingFlags {
+        ItemsHandlingFlags::from_bits_retain(value)
+    }
+}
 
-        /// Items are sent from your starting inventory. Setting this
-        /// automatically sets [OTHER_WORLDS] as well.
-        const STARTING_INVENTORY = 0b101;
+impl From<ItemsHandlingFlags> for u8 {
+    fn from(value: ItemsHandlingFlags) -> u8 {
+        value.bits()
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocationChecks {
-    pub locations: Vec<i64>,
+    pub locations: Vec<LocationId>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocationScouts {
-    pub locations: Vec<i64>,
+    pub locations: Vec<LocationId>,
     pub create_as_hint: u8,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateHint {
-    pub player: i64,
-    pub location: i64,
+    pub player: PlayerId,
+    pub location: LocationId,
     pub status: HintStatus,
 }
 
-#[derive(Debug, Clone, Serialize_repr, Deserialize_repr)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize_repr, Deserialize_repr)]
 #[repr(u16)]
 pub enum HintStatus {
     HintFound = 0,
@@ -230,12 +429,33 @@ pub enum HintStatus {
     HintPriority = 30,
 }
 
+/// A hint as stored by the server in the `_read_hints_{team}_{slot}` data
+/// storage key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hint {
+    pub receiving_player: PlayerId,
+    pub finding_player: PlayerId,
+    pub location: LocationId,
+    pub item: ItemId,
+    pub found: bool,
+    #[serde(default)]
+    pub entrance: String,
+    #[serde(default = "NetworkItemFlags::empty")]
+    pub item_flags: NetworkItemFlags,
+    #[serde(default = "default_hint_status")]
+    pub status: HintStatus,
+}
+
+fn default_hint_status() -> HintStatus {
+    HintStatus::HintUnspecified
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StatusUpdate {
     pub status: ClientStatus,
 }
 
-#[derive(Debug, Clone, Serialize_repr, Deserialize_repr)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize_repr, Deserialize_repr)]
 #[repr(u16)]
 pub enum ClientStatus {
     ClientUnknown = 0,
@@ -256,12 +476,35 @@ pub struct GetDataPackage {
     pub games: Option<Vec<String>>,
 }
 
+/// A message for the server to forward to every client matching any of the
+/// targets. The payload [T] is a dynamic JSON value unless otherwise given.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Bounce {
+pub struct Bounce<T = Value> {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub games: Option<Vec<String>>,
-    pub slots: Option<Vec<String>>,
-    pub tags: Option<Vec<String>>,
-    pub data: Value,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slots: Option<Vec<SlotId>>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<Tag>>,
+    pub data: T,
+}
+
+impl<T> Bounce<T>
+where
+    T: Serialize,
+{
+    /// Converts the payload to dynamic JSON, as sent in a [ClientMessage].
+    pub fn into_untyped(self) -> Result<Bounce, serde_json::Error> {
+        Ok(Bounce {
+            games: self.games,
+            slots: self.slots,
+            tags: self.tags,
+            data: serde_json::to_value(self.data)?,
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -274,7 +517,12 @@ pub struct Set {
     pub key: String,
     pub default: Value,
     pub want_reply: bool,
+    #[serde(serialize_with = "serialize_operations")]
     pub operations: Vec<DataStorageOperation>,
+
+    /// Additional fields, which the server copies into the SetReply.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -300,6 +548,24 @@ pub enum DataStorageOperation {
     Update(Value),
 }
 
+/// Serializes [operations] with a null value for those without an operand,
+/// since the server reads a value for every operation.
+fn serialize_operations<Ser>(
+    operations: &[DataStorageOperation],
+    serializer: Ser,
+) -> Result<Ser::Ok, Ser::Error>
+where
+    Ser: serde::Serializer,
+{
+    serializer.collect_seq(operations.iter().map(|operation| {
+        let mut json = serde_json::to_value(operation).expect("operations always serialize");
+        if let Value::Object(fields) = &mut json {
+            fields.entry("value").or_insert(Value::Null);
+        }
+        json
+    }))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SetNotify {
     pub keys: Vec<String>,
@@ -311,7 +577,7 @@ pub struct SetNotify {
 pub struct RoomInfo {
     pub version: NetworkVersion,
     pub generator_version: NetworkVersion,
-    pub tags: Vec<String>,
+    pub tags: Vec<Tag>,
     #[serde(rename = "password")]
     pub password_required: bool,
     pub permissions: HashMap<String, Permission>,
@@ -326,6 +592,45 @@ pub struct RoomInfo {
     pub time: f64,
 }
 
+impl RoomInfo {
+    /// Applies the room properties that changed in [update].
+    pub fn apply_update(&mut self, update: &RoomUpdate) {
+        if let Some(version) = &update.version {
+            self.version = version.clone();
+        }
+        if let Some(tags) = &update.tags {
+            self.tags = tags.clone();
+        }
+        if let Some(password_required) = update.password_required {
+            self.password_required = password_required;
+        }
+        if let Some(permissions) = &update.permissions {
+            self.permissions = permissions.clone();
+        }
+        if let Some(hint_cost) = update.hint_cost {
+            self.hint_cost = hint_cost;
+        }
+        if let Some(location_check_points) = update.location_check_points {
+            self.location_check_points = location_check_points;
+        }
+        if let Some(games) = &update.games {
+            self.games = games.clone();
+        }
+        if let Some(datapackage_versions) = &update.datapackage_versions {
+            self.datapackage_versions = datapackage_versions.clone();
+        }
+        if let Some(datapackage_checksums) = &update.datapackage_checksums {
+            self.datapackage_checksums = datapackage_checksums.clone();
+        }
+        if let Some(seed_name) = &update.seed_name {
+            self.seed_name = seed_name.clone();
+        }
+        if let Some(time) = update.time {
+            self.time = time;
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectionRefused {
     #[serde(default)]
@@ -335,14 +640,14 @@ pub struct ConnectionRefused {
 #[serde_as]
 #[derive(Debug, Clone, Deserialize)]
 pub struct Connected<S> {
-    pub team: i64,
-    pub slot: i64,
+    pub team: TeamId,
+    pub slot: SlotId,
     pub players: Vec<NetworkPlayer>,
-    pub missing_locations: Vec<i64>,
-    pub checked_locations: Vec<i64>,
+    pub missing_locations: Vec<LocationId>,
+    pub checked_locations: Vec<LocationId>,
     pub slot_data: S,
     #[serde_as(as = "HashMap<DisplayFromStr, _>")]
-    pub slot_info: HashMap<i64, NetworkSlot>,
+    pub slot_info: HashMap<SlotId, NetworkSlot>,
     pub hint_points: i64,
 }
 
@@ -361,7 +666,7 @@ pub struct LocationInfo {
 pub struct RoomUpdate {
     // Copied from RoomInfo
     pub version: Option<NetworkVersion>,
-    pub tags: Option<Vec<String>>,
+    pub tags: Option<Vec<Tag>>,
     #[serde(rename = "password")]
     pub password_required: Option<bool>,
     pub permissions: Option<HashMap<String, Permission>>,
@@ -375,8 +680,8 @@ pub struct RoomUpdate {
     // Exclusive to RoomUpdate
     pub hint_points: Option<i64>,
     pub players: Option<Vec<NetworkPlayer>>,
-    pub checked_locations: Option<Vec<i64>>,
-    pub missing_locations: Option<Vec<i64>>,
+    pub checked_locations: Option<Vec<LocationId>>,
+    pub missing_locations: Option<Vec<LocationId>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -392,36 +697,36 @@ pub struct Print {
 pub enum PrintJSON {
     ItemSend {
         data: Vec<JSONMessagePart>,
-        receiving: i64,
+        receiving: PlayerId,
         item: NetworkItem,
     },
     ItemCheat {
         data: Vec<JSONMessagePart>,
-        receiving: i64,
+        receiving: PlayerId,
         item: NetworkItem,
-        team: i64,
+        team: TeamId,
     },
     Hint {
         data: Vec<JSONMessagePart>,
-        receiving: i64,
+        receiving: PlayerId,
         item: NetworkItem,
         found: bool,
     },
     Join {
         data: Vec<JSONMessagePart>,
-        team: i64,
-        slot: i64,
-        tags: Vec<String>,
+        team: TeamId,
+        slot: SlotId,
+        tags: Vec<Tag>,
     },
     Part {
         data: Vec<JSONMessagePart>,
-        team: i64,
-        slot: i64,
+        team: TeamId,
+        slot: SlotId,
     },
     Chat {
         data: Vec<JSONMessagePart>,
-        team: i64,
-        slot: i64,
+        team: TeamId,
+        slot: SlotId,
         message: String,
     },
     ServerChat {
@@ -433,9 +738,9 @@ pub enum PrintJSON {
     },
     TagsChanged {
         data: Vec<JSONMessagePart>,
-        team: i64,
-        slot: i64,
-        tags: Vec<String>,
+        team: TeamId,
+        slot: SlotId,
+        tags: Vec<Tag>,
     },
     CommandResult {
         data: Vec<JSONMessagePart>,
@@ -445,18 +750,18 @@ pub enum PrintJSON {
     },
     Goal {
         data: Vec<JSONMessagePart>,
-        team: i64,
-        slot: i64,
+        team: TeamId,
+        slot: SlotId,
     },
     Release {
         data: Vec<JSONMessagePart>,
-        team: i64,
-        slot: i64,
+        team: TeamId,
+        slot: SlotId,
     },
     Collect {
         data: Vec<JSONMessagePart>,
-        team: i64,
-        slot: i64,
+        team: TeamId,
+        slot: SlotId,
     },
     Countdown {
         data: Vec<JSONMessagePart>,
@@ -504,7 +809,7 @@ impl PrintJSON {
 impl fmt::Display for PrintJSON {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         for part in self.data() {
-            f.write_str(&part.text().as_str())?;
+            f.write_str(part.text())?;
         }
         Ok(())
     }
@@ -515,7 +820,6 @@ impl fmt::Display for PrintJSON {
 pub enum JSONMessagePart {
     PlayerId {
         text: String,
-        player: i64,
     },
     PlayerName {
         text: String,
@@ -523,20 +827,20 @@ pub enum JSONMessagePart {
     ItemId {
         text: String,
         flags: NetworkItemFlags,
-        player: i64,
+        player: PlayerId,
     },
     ItemName {
         text: String,
         flags: NetworkItemFlags,
-        player: i64,
+        player: PlayerId,
     },
     LocationId {
         text: String,
-        player: i64,
+        player: PlayerId,
     },
     LocationName {
         text: String,
-        player: i64,
+        player: PlayerId,
     },
     EntranceName {
         text: String,
@@ -571,12 +875,12 @@ impl JSONMessagePart {
 
 impl fmt::Display for JSONMessagePart {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        f.write_str(&self.text().as_str())?;
+        f.write_str(self.text())?;
         Ok(())
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum JSONColor {
     Bold,
@@ -611,17 +915,47 @@ pub struct DataPackageObject {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameData {
-    pub item_name_to_id: HashMap<String, i64>,
-    pub location_name_to_id: HashMap<String, i64>,
+    // These keep the order the server sent them in, which the checksum
+    // depends on.
+    pub item_name_to_id: IndexMap<String, ItemId>,
+    pub location_name_to_id: IndexMap<String, LocationId>,
     pub checksum: String,
+
+    // Newer servers leave the name groups out of the data package (they're
+    // available from the `_read_*_name_groups_{game}` data storage keys), but
+    // they're still part of the checksum.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_name_groups: Option<HashMap<String, Vec<String>>>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location_name_groups: Option<HashMap<String, Vec<String>>>,
 }
 
+/// A message forwarded by the server from another client's [Bounce]. The
+/// payload [T] is a dynamic JSON value unless decoded with [Bounced::decode].
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Bounced {
+pub struct Bounced<T = Value> {
     pub games: Option<Vec<String>>,
-    pub slots: Option<Vec<i64>>,
-    pub tags: Vec<String>,
-    pub data: Value,
+    pub slots: Option<Vec<SlotId>>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    pub data: T,
+}
+
+impl Bounced {
+    /// Deserializes the payload as [T].
+    pub fn decode<T>(&self) -> Result<Bounced<T>, serde_json::Error>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(Bounced {
+            games: self.games.clone(),
+            slots: self.slots.clone(),
+            tags: self.tags.clone(),
+            data: T::deserialize(&self.data)?,
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -641,4 +975,65 @@ pub struct SetReply {
     pub key: String,
     pub value: Value,
     pub original_value: Option<Value>, // Won't be there if key is prefixed with _read
+
+    /// The other fields of the reply, including the additional fields of the
+    /// Set that caused it.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_reply_keeps_extra_set_fields() {
+        let set = Set {
+            key: "EnergyLink0".to_string(),
+            default: Value::from(0),
+            want_reply: true,
+            operations: vec![DataStorageOperation::Add(Value::from(5))],
+            extra: serde_json::Map::from_iter([("id".to_string(), Value::from("a-1"))]),
+        };
+        let json = serde_json::to_value(ClientMessage::Set(set)).unwrap();
+        assert_eq!(json["id"], "a-1");
+
+        let reply: Vec<ServerMessage<Value>> = serde_json::from_str(
+            r#"[{"cmd":"SetReply","key":"EnergyLink0","value":5,"original_value":0,"slot":1,"id":"a-1"}]"#,
+        )
+        .unwrap();
+        let [ServerMessage::SetReply(reply)] = &reply[..] else {
+            panic!("expected a SetReply, got {reply:?}");
+        };
+        assert_eq!(reply.original_value, Some(Value::from(0)));
+        assert_eq!(reply.extra.get("id"), Some(&Value::from("a-1")));
+    }
+
+    #[test]
+    fn operations_without_an_operand_send_a_null_value() {
+        let set = Set {
+            key: "key".to_string(),
+            default: Value::from(0),
+            want_reply: false,
+            operations: vec![
+                DataStorageOperation::Default,
+                DataStorageOperation::Floor,
+                DataStorageOperation::Ceil,
+                DataStorageOperation::Add(Value::from(1)),
+            ],
+            extra: serde_json::Map::new(),
+        };
+        let json = serde_json::to_value(&set).unwrap();
+        assert_eq!(
+            json["operations"],
+            serde_json::json!([
+                {"operation": "default", "value": null},
+                {"operation": "floor", "value": null},
+                {"operation": "ceil", "value": null},
+                {"operation": "add", "value": 1},
+            ])
+        );
+        let parsed: Set = serde_json::from_value(json).unwrap();
+        assert!(matches!(parsed.operations[1], DataStorageOperation::Floor));
+    }
 }
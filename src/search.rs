@@ -0,0 +1,336 @@
+//! Fuzzy searching of item and location names.
+
+use std::cmp::Reverse;
+
+use crate::protocol::{GameData, ItemId, LocationId};
+use crate::storage::NameGroups;
+
+/// What kind of name a [SearchMatch] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NameKind {
+    Item,
+    Location,
+    ItemGroup,
+    LocationGroup,
+}
+
+impl NameKind {
+    /// Whether this is an item or an item group.
+    pub fn is_item(self) -> bool {
+        matches!(self, NameKind::Item | NameKind::ItemGroup)
+    }
+
+    /// Whether this is a location or a location group.
+    pub fn is_location(self) -> bool {
+        matches!(self, NameKind::Location | NameKind::LocationGroup)
+    }
+}
+
+/// The ID of the item or location a name belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NameId {
+    Item(ItemId),
+    Location(LocationId),
+}
+
+/// A name that matched a search, along with how well it matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch<'a> {
+    pub name: &'a str,
+    pub kind: NameKind,
+
+    /// The ID of the item or location, or `None` for a group.
+    pub id: Option<NameId>,
+
+    /// How well the name matched. Higher is better, and scores are only
+    /// meaningful relative to other matches for the same query.
+    pub score: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    name: String,
+    kind: NameKind,
+    id: Option<NameId>,
+    words: Vec<String>,
+}
+
+/// An index of one game's item and location names, and optionally its name
+/// groups, that ranks names against partial or misspelled queries.
+///
+/// Matching ignores case and punctuation. From best to worst, names are
+/// ranked by whether the query matches them exactly, is a prefix of them,
+/// starts a word of them, appears in them, starts words in them (such as
+/// "de pa" for "Desert Palace"), is an abbreviation of them, or is within a
+/// couple of typos of them or some of their words.
+#[derive(Debug, Clone, Default)]
+pub struct NameIndex {
+    entries: Vec<Entry>,
+}
+
+impl NameIndex {
+    /// Indexes the item and location names of [data], along with its name
+    /// groups if it includes them.
+    pub fn new(data: &GameData) -> NameIndex {
+        let mut index = NameIndex::default();
+        for (name, id) in &data.item_name_to_id {
+            index.insert(name, NameKind::Item, Some(NameId::Item(*id)));
+        }
+        for (name, id) in &data.location_name_to_id {
+            index.insert(name, NameKind::Location, Some(NameId::Location(*id)));
+        }
+        if let Some(groups) = &data.item_name_groups {
+            index = index.with_item_groups(groups);
+        }
+        if let Some(groups) = &data.location_name_groups {
+            index = index.with_location_groups(groups);
+        }
+        index
+    }
+
+    /// Adds the names of item [groups], such as those read with
+    /// `ArchipelagoClient::read_item_name_groups`.
+    pub fn with_item_groups(mut self, groups: &NameGroups) -> NameIndex {
+        for name in groups.keys() {
+            self.insert(name, NameKind::ItemGroup, None);
+        }
+        self
+    }
+
+    /// Adds the names of location [groups], such as those read with
+    /// `ArchipelagoClient::read_location_name_groups`.
+    pub fn with_location_groups(mut self, groups: &NameGroups) -> NameIndex {
+        for name in groups.keys() {
+            self.insert(name, NameKind::LocationGroup, None);
+        }
+        self
+    }
+
+    fn insert(&mut self, name: &str, kind: NameKind, id: Option<NameId>) {
+        self.entries.push(Entry {
+            name: name.to_string(),
+            kind,
+            id,
+            words: words(name),
+        });
+    }
+
+    /// Returns up to [limit] names matching [query], best first.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchMatch<'_>> {
+        self.search_where(query, limit, |_| true)
+    }
+
+    /// Returns up to [limit] item and item group names matching [query],
+    /// best first.
+    pub fn search_items(&self, query: &str, limit: usize) -> Vec<SearchMatch<'_>> {
+        self.search_where(query, limit, NameKind::is_item)
+    }
+
+    /// Returns up to [limit] location and location group names matching
+    /// [query], best first.
+    pub fn search_locations(&self, query: &str, limit: usize) -> Vec<SearchMatch<'_>> {
+        self.search_where(query, limit, NameKind::is_location)
+    }
+
+    fn search_where(
+        &self,
+        query: &str,
+        limit: usize,
+        filter: impl Fn(NameKind) -> bool,
+    ) -> Vec<SearchMatch<'_>> {
+        let query = words(query);
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let mut matches: Vec<SearchMatch> = self
+            .entries
+            .iter()
+            .filter(|entry| filter(entry.kind))
+            .filter_map(|entry| {
+                Some(SearchMatch {
+                    name: &entry.name,
+                    kind: entry.kind,
+                    id: entry.id,
+                    score: score(&query, &entry.words)?,
+                })
+            })
+            .collect();
+        // Among equally good matches, prefer shorter names, since the query
+        // covers more of them.
+        matches.sort_by_key(|m| (Reverse(m.score), m.name.len(), m.name));
+        matches.truncate(limit);
+        matches
+    }
+}
+
+/// Splits [text] into lowercase alphanumeric words.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Scores how well [query] matches [name], both split into words, or returns
+/// `None` if it doesn't.
+fn score(query: &[String], name: &[String]) -> Option<u32> {
+    let query_joined = query.concat();
+    let name_joined = name.concat();
+    let tier = if query == name {
+        7
+    } else if name_joined.starts_with(&query_joined) {
+        6
+    } else if (0..name.len()).any(|i| name[i..].concat().starts_with(&query_joined)) {
+        5
+    } else if name_joined.contains(&query_joined) {
+        4
+    } else if query
+        .iter()
+        .all(|q| name.iter().any(|word| word.starts_with(q.as_str())))
+    {
+        3
+    } else if is_subsequence(&query_joined, &name_joined) && query_joined.len() > 1 {
+        2
+    } else {
+        let typos = typos(&query_joined, query.len(), name)?;
+        return Some(100 - 40 * typos);
+    };
+    Some(tier * 100 + 100)
+}
+
+/// Returns whether the characters of [needle] appear in [haystack] in order.
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
+
+/// The fewest typos that turn [query] into the whole of [name] or a run of
+/// [query_words] of its words, if there are few enough for the query's
+/// length.
+fn typos(query: &str, query_words: usize, name: &[String]) -> Option<u32> {
+    let max_typos = match query.chars().count() {
+        0..=2 => 0,
+        3..=7 => 1,
+        _ => 2,
+    };
+    if max_typos == 0 || name.is_empty() {
+        return None;
+    }
+    let whole = std::iter::once(name.concat());
+    let runs = name
+        .windows(query_words.min(name.len()))
+        .map(|window| window.concat());
+    whole
+        .chain(runs)
+        .map(|candidate| edit_distance(query, &candidate))
+        .min()
+        .filter(|typos| *typos <= max_typos)
+}
+
+/// The number of insertions, deletions, substitutions and swaps of adjacent
+/// characters needed to turn [a] into [b].
+fn edit_distance(a: &str, b: &str) -> u32 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i as u32;
+    }
+    rows[0] = (0..=b.len() as u32).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = u32::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score_of(query: &str, name: &str) -> Option<u32> {
+        score(&words(query), &words(name))
+    }
+
+    #[test]
+    fn ranks_match_tiers() {
+        assert_eq!(score_of("hookshot", "Hookshot"), Some(800));
+        assert_eq!(score_of("hook", "Hookshot"), Some(700));
+        assert_eq!(score_of("shot", "Hook Shot"), Some(600));
+        assert_eq!(score_of("ksho", "Hookshot"), Some(500));
+        assert_eq!(score_of("de pa", "Desert Palace"), Some(400));
+        assert_eq!(score_of("dp", "Desert Palace"), Some(300));
+    }
+
+    #[test]
+    fn ignores_case_and_punctuation() {
+        assert_eq!(score_of("hook-shot!", "Hook Shot"), Some(800));
+        assert_eq!(score_of("DESERT palace", "Desert Palace"), Some(800));
+    }
+
+    #[test]
+    fn allows_typos_by_query_length() {
+        assert_eq!(score_of("hookshit", "Hookshot"), Some(60));
+        assert_eq!(score_of("hookshto", "Hookshot"), Some(60));
+        assert_eq!(score_of("hoikshit", "Hookshot"), Some(20));
+        assert_eq!(score_of("hxxkshxt", "Hookshot"), None);
+        assert_eq!(score_of("bpw", "Bow"), Some(60));
+        assert_eq!(score_of("bx", "Bow"), None);
+    }
+
+    #[test]
+    fn matches_typos_against_runs_of_words() {
+        assert_eq!(score_of("palcae", "Desert Palace"), Some(60));
+        assert_eq!(score_of("desert plaace", "Desert Palace"), Some(60));
+    }
+
+    #[test]
+    fn handles_non_ascii_names() {
+        assert_eq!(score_of("épée", "Épée"), Some(800));
+        assert_eq!(score_of("épé", "Épée"), Some(700));
+        assert_eq!(score_of("épee", "Épée"), Some(60));
+        assert_eq!(score_of("epee", "Épée"), None);
+    }
+
+    #[test]
+    fn counts_edits_by_character() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("ab", "ba"), 1);
+        assert_eq!(edit_distance("é", "e"), 1);
+    }
+
+    #[test]
+    fn searches_best_first() {
+        let data: GameData = serde_json::from_str(
+            r#"{
+                "item_name_to_id": {"Master Sword": 1, "Sword": 2, "Swordfish": 3},
+                "location_name_to_id": {"Sword Room": 10},
+                "checksum": ""
+            }"#,
+        )
+        .unwrap();
+        let index = NameIndex::new(&data);
+        assert!(index.search("", 10).is_empty());
+
+        let names: Vec<&str> = index.search("sword", 10).iter().map(|m| m.name).collect();
+        assert_eq!(names, ["Sword", "Swordfish", "Sword Room", "Master Sword"]);
+
+        let items = index.search_items("sword", 2);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].id, Some(NameId::Item(ItemId(2))));
+
+        let locations = index.search_locations("sword", 10);
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].id, Some(NameId::Location(LocationId(10))));
+    }
+}
@@ -0,0 +1,75 @@
+//! Saving a session so it can be resumed after the game is relaunched.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::client::{ArchipelagoError, ConnectionInfo};
+use crate::protocol::{ItemsHandlingFlags, LocationId, RoomInfo, SlotId, Tag, TeamId};
+
+/// Everything needed to reconnect to the same slot of the same seed and pick
+/// up where the player left off.
+///
+/// Snapshots are meant to be written next to the game's save file, and are
+/// refused when the room no longer hosts the seed or slot they came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    pub seed_name: String,
+    pub game: String,
+    pub name: String,
+    pub team: TeamId,
+    pub slot: SlotId,
+
+    /// The number of received items the game has applied.
+    pub item_index: i64,
+
+    /// The locations known to be checked, including any checked offline.
+    pub checked_locations: BTreeSet<LocationId>,
+    pub tags: Vec<Tag>,
+    pub items_handling: ItemsHandlingFlags,
+
+    /// The checksums of the data package games that were loaded.
+    #[serde(default)]
+    pub datapackage_checksums: HashMap<String, String>,
+}
+
+impl SessionSnapshot {
+    /// Reads a snapshot previously written with [SessionSnapshot::save].
+    pub fn load(path: impl AsRef<Path>) -> Result<SessionSnapshot, ArchipelagoError> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json)
+            .map_err(|error| ArchipelagoError::FailedDeserialize { json, error })
+    }
+
+    /// Writes this snapshot to [path] as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ArchipelagoError> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Returns an error unless [room_info] hosts the seed this snapshot came
+    /// from.
+    pub fn check_room(&self, room_info: &RoomInfo) -> Result<(), ArchipelagoError> {
+        if room_info.seed_name != self.seed_name {
+            return Err(ArchipelagoError::SessionMismatch(format!(
+                "expected seed {}, but the room is hosting seed {}",
+                self.seed_name, room_info.seed_name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns an error unless [connection] is to the team and slot this
+    /// snapshot came from.
+    pub fn check_connection(&self, connection: &ConnectionInfo) -> Result<(), ArchipelagoError> {
+        if (connection.team, connection.slot) != (self.team, self.slot) {
+            return Err(ArchipelagoError::SessionMismatch(format!(
+                "expected team {} slot {}, but connected to team {} slot {}",
+                self.team, self.slot, connection.team, connection.slot
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,416 @@
+//! Support for the server's data storage.
+
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::mpsc::{self, error::TryRecvError, UnboundedReceiver, UnboundedSender};
+
+use crate::client::ArchipelagoError;
+use crate::protocol::{
+    ClientStatus, DataStorageOperation, Hint, Retrieved, ServerMessage, Set, SetReply, SlotId,
+    TeamId,
+};
+
+/// A change to a watched data storage key, as reported by the server in a
+/// [SetReply].
+#[derive(Debug, Clone)]
+pub struct KeyChange<T> {
+    pub key: String,
+    pub value: T,
+
+    /// The value before the change. This is absent for the server's read-only
+    /// keys.
+    pub original_value: Option<T>,
+}
+
+/// A stream of changes to a single watched data storage key.
+///
+/// Changes are only delivered while the client is receiving messages, so this
+/// is meant to be polled alongside `ArchipelagoClient::recv` (for example with
+/// `tokio::select!`). The stream ends once the client is dropped.
+pub struct Subscription<T> {
+    key: String,
+    receiver: UnboundedReceiver<SetReply>,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> Subscription<T>
+where
+    T: DeserializeOwned,
+{
+    /// The key this subscription watches.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Waits for the next change to the watched key.
+    pub async fn next(&mut self) -> Option<Result<KeyChange<T>, ArchipelagoError>> {
+        let reply = self.receiver.recv().await?;
+        Some(decode_change(reply))
+    }
+
+    /// Returns the next change to the watched key if one has already arrived.
+    pub fn try_next(&mut self) -> Option<Result<KeyChange<T>, ArchipelagoError>> {
+        match self.receiver.try_recv() {
+            Ok(reply) => Some(decode_change(reply)),
+            Err(TryRecvError::Empty | TryRecvError::Disconnected) => None,
+        }
+    }
+}
+
+pub(crate) fn decode_change<T>(reply: SetReply) -> Result<KeyChange<T>, ArchipelagoError>
+where
+    T: DeserializeOwned,
+{
+    Ok(KeyChange {
+        key: reply.key,
+        value: decode_value(reply.value)?,
+        original_value: reply.original_value.map(decode_value).transpose()?,
+    })
+}
+
+/// Deserializes a data storage value, reporting the JSON on failure.
+pub(crate) fn decode_value<T>(value: Value) -> Result<T, ArchipelagoError>
+where
+    T: DeserializeOwned,
+{
+    serde_json::from_value(value.clone()).map_err(|error| ArchipelagoError::FailedDeserialize {
+        json: value.to_string(),
+        error,
+    })
+}
+
+/// A local copy of the data storage keys the client is watching.
+///
+/// Watched keys are seeded with an initial `Get` and then kept current from
+/// the `SetReply` notifications the server sends for them.
+#[derive(Default)]
+pub struct StorageMirror {
+    values: HashMap<String, Value>,
+    watched: HashSet<String>,
+    subscribers: HashMap<String, Vec<UnboundedSender<SetReply>>>,
+}
+
+impl StorageMirror {
+    /// Returns whether [key] is being watched.
+    pub fn is_watched(&self, key: &str) -> bool {
+        self.watched.contains(key)
+    }
+
+    /// Returns the latest known value of [key], if it's watched and its value
+    /// has arrived.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.values.get(key)
+    }
+
+    /// Returns the latest known value of [key] deserialized as [T].
+    pub fn get_as<T>(&self, key: &str) -> Option<Result<T, ArchipelagoError>>
+    where
+        T: DeserializeOwned,
+    {
+        self.get(key).cloned().map(decode_value)
+    }
+
+    /// Marks [keys] as watched, returning the ones that weren't already.
+    pub(crate) fn watch(&mut self, keys: Vec<String>) -> Vec<String> {
+        keys.into_iter()
+            .filter(|key| self.watched.insert(key.clone()))
+            .collect()
+    }
+
+    pub(crate) fn subscribe<T>(&mut self, key: &str) -> Subscription<T> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.subscribers
+            .entry(key.to_string())
+            .or_default()
+            .push(sender);
+        Subscription {
+            key: key.to_string(),
+            receiver,
+            _value: PhantomData,
+        }
+    }
+
+    /// Updates the mirror from a message the client just received.
+    pub(crate) fn handle_message<S>(&mut self, message: &ServerMessage<S>) {
+        match message {
+            ServerMessage::Retrieved(retrieved) => self.handle_retrieved(retrieved),
+            ServerMessage::SetReply(reply) => self.handle_set_reply(reply),
+            _ => {}
+        }
+    }
+
+    fn handle_retrieved(&mut self, retrieved: &Retrieved) {
+        let Value::Object(keys) = &retrieved.keys else {
+            return;
+        };
+        for (key, value) in keys {
+            if self.watched.contains(key) {
+                self.values.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    fn handle_set_reply(&mut self, reply: &SetReply) {
+        if !self.watched.contains(&reply.key) {
+            return;
+        }
+        self.values.insert(reply.key.clone(), reply.value.clone());
+        if let Some(subscribers) = self.subscribers.get_mut(&reply.key) {
+            subscribers.retain(|subscriber| subscriber.send(reply.clone()).is_ok());
+        }
+    }
+}
+
+/// A typed handle to a single data storage key whose value is a [T].
+///
+/// The handle itself holds no data; pass it to the client's `read`, `write`
+/// and `subscribe_to` methods, or start a write with [DataStorage::set].
+#[derive(Debug)]
+pub struct DataStorage<T> {
+    key: String,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for DataStorage<T> {
+    fn clone(&self) -> Self {
+        DataStorage::new(self.key.clone())
+    }
+}
+
+impl<T> DataStorage<T> {
+    /// A handle for the exact key [key].
+    pub fn new(key: impl Into<String>) -> DataStorage<T> {
+        DataStorage {
+            key: key.into(),
+            _value: PhantomData,
+        }
+    }
+
+    /// A handle for [name] namespaced to a team, shared by all of its slots.
+    pub fn for_team(name: &str, team: TeamId) -> DataStorage<T> {
+        DataStorage::new(format!("{name}_{team}"))
+    }
+
+    /// A handle for [name] namespaced to a single slot on a team.
+    pub fn for_slot(name: &str, team: TeamId, slot: SlotId) -> DataStorage<T> {
+        DataStorage::new(format!("{name}_{team}_{slot}"))
+    }
+
+    /// A handle for [name] namespaced to a game, shared by every slot playing
+    /// it.
+    pub fn for_game(name: &str, game: &str) -> DataStorage<T> {
+        DataStorage::new(format!("{name}_{game}"))
+    }
+
+    /// The read-only slot data of [slot], decoded as [T].
+    pub fn slot_data(slot: SlotId) -> DataStorage<T> {
+        DataStorage::new(format!("_read_slot_data_{slot}"))
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+/// Item and location name groups, mapping each group name to its members.
+pub type NameGroups = HashMap<String, Vec<String>>;
+
+impl DataStorage<Vec<Hint>> {
+    /// The read-only list of hints relevant to [slot] on [team].
+    pub fn hints(team: TeamId, slot: SlotId) -> DataStorage<Vec<Hint>> {
+        DataStorage::new(format!("_read_hints_{team}_{slot}"))
+    }
+}
+
+impl DataStorage<NameGroups> {
+    /// The read-only item name groups of [game].
+    pub fn item_name_groups(game: &str) -> DataStorage<NameGroups> {
+        DataStorage::new(format!("_read_item_name_groups_{game}"))
+    }
+
+    /// The read-only location name groups of [game].
+    pub fn location_name_groups(game: &str) -> DataStorage<NameGroups> {
+        DataStorage::new(format!("_read_location_name_groups_{game}"))
+    }
+}
+
+impl DataStorage<ClientStatus> {
+    /// The read-only client status of [slot] on [team].
+    pub fn client_status(team: TeamId, slot: SlotId) -> DataStorage<ClientStatus> {
+        DataStorage::new(format!("_read_client_status_{team}_{slot}"))
+    }
+}
+
+impl DataStorage<i64> {
+    /// The read-only race mode flag of the room, which is 1 when race mode is
+    /// enabled and 0 otherwise.
+    pub fn race_mode() -> DataStorage<i64> {
+        DataStorage::new("_read_race_mode")
+    }
+}
+
+impl<T> DataStorage<T>
+where
+    T: Serialize,
+{
+    /// Starts building a write to this key.
+    pub fn set(&self) -> SetBuilder<T> {
+        SetBuilder {
+            key: self.key.clone(),
+            default: Value::Null,
+            operations: Vec::new(),
+            error: None,
+            _value: PhantomData,
+        }
+    }
+}
+
+/// A fluent builder for the operations of a single [Set] on a typed key.
+///
+/// Operations are applied by the server in the order they were added.
+/// Operands that fail to serialize are reported when the write is sent.
+pub struct SetBuilder<T> {
+    key: String,
+    default: Value,
+    operations: Vec<DataStorageOperation>,
+    error: Option<serde_json::Error>,
+    _value: PhantomData<fn() -> T>,
+}
+
+// The operations are named after the protocol's, even where that shadows
+// operator traits like `Add`.
+#[allow(clippy::should_implement_trait)]
+impl<T> SetBuilder<T>
+where
+    T: Serialize,
+{
+    /// Sets the value the key starts from if it doesn't exist yet.
+    pub fn with_default(mut self, default: T) -> Self {
+        if let Some(default) = self.serialize_operand(default) {
+            self.default = default;
+        }
+        self
+    }
+
+    /// Replaces the current value.
+    pub fn replace(self, value: T) -> Self {
+        self.operation_with(value, DataStorageOperation::Replace)
+    }
+
+    /// Sets the current value to the default if the key doesn't exist yet.
+    pub fn default(self) -> Self {
+        self.operation(DataStorageOperation::Default)
+    }
+
+    /// Adds to a number, or appends to a list.
+    pub fn add(self, value: impl Serialize) -> Self {
+        self.operation_with(value, DataStorageOperation::Add)
+    }
+
+    pub fn mul(self, value: impl Serialize) -> Self {
+        self.operation_with(value, DataStorageOperation::Mul)
+    }
+
+    pub fn pow(self, value: impl Serialize) -> Self {
+        self.operation_with(value, DataStorageOperation::Pow)
+    }
+
+    pub fn modulo(self, value: impl Serialize) -> Self {
+        self.operation_with(value, DataStorageOperation::Mod)
+    }
+
+    pub fn floor(self) -> Self {
+        self.operation(DataStorageOperation::Floor)
+    }
+
+    pub fn ceil(self) -> Self {
+        self.operation(DataStorageOperation::Ceil)
+    }
+
+    pub fn max(self, value: impl Serialize) -> Self {
+        self.operation_with(value, DataStorageOperation::Max)
+    }
+
+    pub fn min(self, value: impl Serialize) -> Self {
+        self.operation_with(value, DataStorageOperation::Min)
+    }
+
+    pub fn and(self, value: impl Serialize) -> Self {
+        self.operation_with(value, DataStorageOperation::And)
+    }
+
+    pub fn or(self, value: impl Serialize) -> Self {
+        self.operation_with(value, DataStorageOperation::Or)
+    }
+
+    pub fn xor(self, value: impl Serialize) -> Self {
+        self.operation_with(value, DataStorageOperation::Xor)
+    }
+
+    pub fn left_shift(self, value: impl Serialize) -> Self {
+        self.operation_with(value, DataStorageOperation::LeftShift)
+    }
+
+    pub fn right_shift(self, value: impl Serialize) -> Self {
+        self.operation_with(value, DataStorageOperation::RightShift)
+    }
+
+    /// Removes the first instance of [value] from a list.
+    pub fn remove(self, value: impl Serialize) -> Self {
+        self.operation_with(value, DataStorageOperation::Remove)
+    }
+
+    /// Removes the element at an index of a list, or a key of a dictionary.
+    pub fn pop(self, value: impl Serialize) -> Self {
+        self.operation_with(value, DataStorageOperation::Pop)
+    }
+
+    /// Merges a dictionary into the current dictionary value.
+    pub fn update(self, value: impl Serialize) -> Self {
+        self.operation_with(value, DataStorageOperation::Update)
+    }
+
+    /// Builds the [Set] message for these operations.
+    pub fn build(self, want_reply: bool) -> Result<Set, ArchipelagoError> {
+        if let Some(error) = self.error {
+            return Err(ArchipelagoError::FailedSerialize(error));
+        }
+        Ok(Set {
+            key: self.key,
+            default: self.default,
+            want_reply,
+            operations: self.operations,
+            extra: serde_json::Map::new(),
+        })
+    }
+
+    fn operation(mut self, operation: DataStorageOperation) -> Self {
+        self.operations.push(operation);
+        self
+    }
+
+    fn operation_with(
+        mut self,
+        value: impl Serialize,
+        operation: impl FnOnce(Value) -> DataStorageOperation,
+    ) -> Self {
+        if let Some(value) = self.serialize_operand(value) {
+            self.operations.push(operation(value));
+        }
+        self
+    }
+
+    fn serialize_operand(&mut self, value: impl Serialize) -> Option<Value> {
+        match serde_json::to_value(value) {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.error.get_or_insert(error);
+                None
+            }
+        }
+    }
+}
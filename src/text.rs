@@ -0,0 +1,384 @@
+//! Rendering PrintJSON messages as human-readable text.
+
+use std::fmt::Write;
+
+use crate::client::ConnectionInfo;
+use crate::lookup::{NameLookup, ARCHIPELAGO_GAME, SERVER_SLOT};
+use crate::protocol::{
+    ItemId, JSONColor, JSONMessagePart, LocationId, NetworkItemFlags, PlayerId, PrintJSON, SlotId,
+};
+
+/// Resolves the IDs in [JSONMessagePart]s to the names of the players, items
+/// and locations they refer to.
+///
+/// Players are named by their alias on the connected team, falling back to
+/// their slot name. Items and locations are named using the data package of
+/// the game of the player they belong to. IDs that can't be resolved are
+/// rendered the way the official client renders them, such as
+/// `Unknown item (ID:1234)`.
+#[derive(Debug, Clone, Copy)]
+pub struct TextResolver<'a> {
+    connection: &'a ConnectionInfo,
+    lookup: Option<&'a NameLookup>,
+}
+
+impl<'a> TextResolver<'a> {
+    /// Creates a resolver for [connection]'s players. Without a [lookup],
+    /// item and location IDs aren't resolved.
+    pub fn new(connection: &'a ConnectionInfo, lookup: Option<&'a NameLookup>) -> TextResolver<'a> {
+        TextResolver { connection, lookup }
+    }
+
+    /// The name of the player in [slot] on the connected team.
+    pub fn player_name(&self, slot: SlotId) -> String {
+        if let Some(player) = self.connection.player(slot) {
+            return player.alias.clone();
+        }
+        match self.connection.slot_info.get(&slot) {
+            Some(info) => info.name.clone(),
+            None if slot == SERVER_SLOT => ARCHIPELAGO_GAME.to_string(),
+            None => format!("Unknown player (ID:{slot})"),
+        }
+    }
+
+    /// The game played in [slot], if it's known.
+    pub fn player_game(&self, slot: SlotId) -> Option<&'a str> {
+        match self.connection.slot_info.get(&slot) {
+            Some(info) => Some(&info.game),
+            None if slot == SERVER_SLOT => Some(ARCHIPELAGO_GAME),
+            None => None,
+        }
+    }
+
+    /// The connected slot, which received items belong to.
+    pub fn own_slot(&self) -> SlotId {
+        self.connection.slot
+    }
+
+    /// The name of [item] in [player]'s game.
+    pub fn item_name(&self, player: PlayerId, item: ItemId) -> String {
+        self.lookup
+            .and_then(|lookup| lookup.item_name(player, item))
+            .map_or_else(|| format!("Unknown item (ID:{item})"), str::to_string)
+    }
+
+    /// The name of [location] in [player]'s game.
+    pub fn location_name(&self, player: PlayerId, location: LocationId) -> String {
+        self.lookup
+            .and_then(|lookup| lookup.location_name(player, location))
+            .map_or_else(
+                || format!("Unknown location (ID:{location})"),
+                str::to_string,
+            )
+    }
+
+    /// The text [part] should be displayed as.
+    ///
+    /// Parts that carry an ID in their text are resolved to a name, and all
+    /// other parts are displayed as their text.
+    pub fn resolve(&self, part: &JSONMessagePart) -> String {
+        let id = part.text().parse::<i64>();
+        match (part, id) {
+            (JSONMessagePart::PlayerId { .. }, Ok(slot)) => self.player_name(SlotId(slot)),
+            (JSONMessagePart::ItemId { player, .. }, Ok(item)) => {
+                self.item_name(*player, ItemId(item))
+            }
+            (JSONMessagePart::LocationId { player, .. }, Ok(location)) => {
+                self.location_name(*player, LocationId(location))
+            }
+            _ => part.text().clone(),
+        }
+    }
+
+    /// The color the official client displays [part] in, if any.
+    ///
+    /// The connected player is magenta and other players are yellow.
+    /// Progression items are plum, useful items slate blue, traps salmon and
+    /// other items cyan. Locations are green and entrances blue.
+    pub fn color(&self, part: &JSONMessagePart) -> Option<TextColor> {
+        match part {
+            JSONMessagePart::PlayerId { text, .. } if text.parse() == Ok(self.connection.slot) => {
+                Some(TextColor::Magenta)
+            }
+            JSONMessagePart::PlayerName { text }
+                if self.connection.name() == Some(text.as_str()) =>
+            {
+                Some(TextColor::Magenta)
+            }
+            JSONMessagePart::PlayerId { .. } | JSONMessagePart::PlayerName { .. } => {
+                Some(TextColor::Yellow)
+            }
+            JSONMessagePart::ItemId { flags, .. } | JSONMessagePart::ItemName { flags, .. } => {
+                Some(TextColor::for_item(flags))
+            }
+            JSONMessagePart::LocationId { .. } | JSONMessagePart::LocationName { .. } => {
+                Some(TextColor::Green)
+            }
+            JSONMessagePart::EntranceName { .. } => Some(TextColor::Blue),
+            JSONMessagePart::Color { color, .. } => Some(TextColor::from(*color)),
+            JSONMessagePart::Text { .. } => None,
+        }
+    }
+
+    /// Renders [message] as plain text with every ID resolved, such as
+    /// "Alice found Bob's Hookshot at Desert Palace".
+    pub fn render(&self, message: &PrintJSON) -> String {
+        message
+            .data()
+            .iter()
+            .map(|part| self.resolve(part))
+            .collect()
+    }
+
+    /// Renders [message] with every ID resolved, styled by [renderer].
+    pub fn render_with(&self, message: &PrintJSON, renderer: &impl Renderer) -> String {
+        let mut out = String::new();
+        for part in message.data() {
+            renderer.render_part(&mut out, part, &self.resolve(part), self.color(part));
+        }
+        out
+    }
+}
+
+/// The colors and text styles a message part can be displayed in: those of
+/// [JSONColor], plus the extra colors the official client uses for items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextColor {
+    Bold,
+    Underline,
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BlackBg,
+    RedBg,
+    GreenBg,
+    YellowBg,
+    BlueBg,
+    MagentaBg,
+    CyanBg,
+    WhiteBg,
+    Plum,
+    SlateBlue,
+    Salmon,
+}
+
+impl TextColor {
+    /// The color of an item with [flags].
+    pub fn for_item(flags: &NetworkItemFlags) -> TextColor {
+        if flags.contains(NetworkItemFlags::PROGRESSION) {
+            TextColor::Plum
+        } else if flags.contains(NetworkItemFlags::USEFUL) {
+            TextColor::SlateBlue
+        } else if flags.contains(NetworkItemFlags::TRAP) {
+            TextColor::Salmon
+        } else {
+            TextColor::Cyan
+        }
+    }
+
+    /// The snake_case name the official client uses for this color.
+    pub fn name(self) -> &'static str {
+        use TextColor::*;
+        match self {
+            Bold => "bold",
+            Underline => "underline",
+            Black => "black",
+            Red => "red",
+            Green => "green",
+            Yellow => "yellow",
+            Blue => "blue",
+            Magenta => "magenta",
+            Cyan => "cyan",
+            White => "white",
+            BlackBg => "black_bg",
+            RedBg => "red_bg",
+            GreenBg => "green_bg",
+            YellowBg => "yellow_bg",
+            BlueBg => "blue_bg",
+            MagentaBg => "magenta_bg",
+            CyanBg => "cyan_bg",
+            WhiteBg => "white_bg",
+            Plum => "plum",
+            SlateBlue => "slateblue",
+            Salmon => "salmon",
+        }
+    }
+
+    /// The ANSI SGR code for this color. Terminals don't have the item
+    /// colors, so they're approximated the same way the official client
+    /// does.
+    pub fn ansi_code(self) -> u8 {
+        use TextColor::*;
+        match self {
+            Bold => 1,
+            Underline => 4,
+            Black => 30,
+            Red | Salmon => 31,
+            Green => 32,
+            Yellow => 33,
+            Blue | SlateBlue => 34,
+            Magenta | Plum => 35,
+            Cyan => 36,
+            White => 37,
+            BlackBg => 40,
+            RedBg => 41,
+            GreenBg => 42,
+            YellowBg => 43,
+            BlueBg => 44,
+            MagentaBg => 45,
+            CyanBg => 46,
+            WhiteBg => 47,
+        }
+    }
+}
+
+impl From<JSONColor> for TextColor {
+    fn from(color: JSONColor) -> TextColor {
+        use JSONColor::*;
+        match color {
+            Bold => TextColor::Bold,
+            Underline => TextColor::Underline,
+            Black => TextColor::Black,
+            Red => TextColor::Red,
+            Green => TextColor::Green,
+            Yellow => TextColor::Yellow,
+            Blue => TextColor::Blue,
+            Magenta => TextColor::Magenta,
+            Cyan => TextColor::Cyan,
+            White => TextColor::White,
+            BlackBg => TextColor::BlackBg,
+            RedBg => TextColor::RedBg,
+            GreenBg => TextColor::GreenBg,
+            YellowBg => TextColor::YellowBg,
+            BlueBg => TextColor::BlueBg,
+            MagentaBg => TextColor::MagentaBg,
+            CyanBg => TextColor::CyanBg,
+            WhiteBg => TextColor::WhiteBg,
+        }
+    }
+}
+
+/// A backend for [TextResolver::render_with] that styles each part of a
+/// message for some output format.
+pub trait Renderer {
+    /// Appends [part] to [out], where [text] is the part's resolved text and
+    /// [color] the color the official client would display it in.
+    ///
+    /// [text] can come from other players, so implementations must make sure
+    /// it can't inject markup or control sequences.
+    fn render_part(
+        &self,
+        out: &mut String,
+        part: &JSONMessagePart,
+        text: &str,
+        color: Option<TextColor>,
+    );
+}
+
+/// Renders messages for terminals, using ANSI escape codes for colors.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AnsiRenderer;
+
+impl Renderer for AnsiRenderer {
+    fn render_part(
+        &self,
+        out: &mut String,
+        _part: &JSONMessagePart,
+        text: &str,
+        color: Option<TextColor>,
+    ) {
+        let text = sanitize(text);
+        match color {
+            Some(color) => {
+                let _ = write!(out, "\x1b[{}m{text}\x1b[0m", color.ansi_code());
+            }
+            None => out.push_str(&text),
+        }
+    }
+}
+
+/// Renders messages as HTML, wrapping each colored part in a span with the
+/// class `ap-{color}`, such as `ap-plum` or `ap-magenta_bg`, for the page's
+/// CSS to style.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HtmlRenderer;
+
+impl Renderer for HtmlRenderer {
+    fn render_part(
+        &self,
+        out: &mut String,
+        _part: &JSONMessagePart,
+        text: &str,
+        color: Option<TextColor>,
+    ) {
+        let mut escaped = String::new();
+        for c in sanitize(text).chars() {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&#39;"),
+                c => escaped.push(c),
+            }
+        }
+        match color {
+            Some(color) => {
+                let _ = write!(out, "<span class=\"ap-{}\">{escaped}</span>", color.name());
+            }
+            None => out.push_str(&escaped),
+        }
+    }
+}
+
+/// Renders messages as Markdown, which has no colors: players and bold text
+/// are bold, items are italic, and progression items are both.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarkdownRenderer;
+
+impl Renderer for MarkdownRenderer {
+    fn render_part(
+        &self,
+        out: &mut String,
+        part: &JSONMessagePart,
+        text: &str,
+        color: Option<TextColor>,
+    ) {
+        let mut escaped = String::new();
+        for c in sanitize(text).chars() {
+            if "\\`*_{}[]<>()#+-.!|~".contains(c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        if escaped.trim().is_empty() {
+            // Emphasis around whitespace isn't parsed as emphasis.
+            out.push_str(&escaped);
+            return;
+        }
+        let emphasis = match (part, color) {
+            (
+                JSONMessagePart::ItemId { .. } | JSONMessagePart::ItemName { .. },
+                Some(TextColor::Plum),
+            ) => "***",
+            (JSONMessagePart::ItemId { .. } | JSONMessagePart::ItemName { .. }, _) => "*",
+            (JSONMessagePart::PlayerId { .. } | JSONMessagePart::PlayerName { .. }, _) => "**",
+            (_, Some(TextColor::Bold)) => "**",
+            _ => "",
+        };
+        let _ = write!(out, "{emphasis}{escaped}{emphasis}");
+    }
+}
+
+/// Removes control characters other than newlines and tabs from [text], so
+/// that text from other players can't smuggle in escape sequences.
+fn sanitize(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+        .collect()
+}